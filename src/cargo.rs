@@ -0,0 +1,632 @@
+//! Locating Cargo target directories, plus the sweep mode that removes only stale artifacts and the cleanup selected by profile or target triple
+
+use crate::size::{DirStats, HardLinkTracker, SizeBudget, SizeEngine};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs::Metadata;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+
+/// Cleaning options for Cargo projects (`[cargo]` in the config file)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CargoOptions {
+    /// Sweep mode: instead of running `cargo clean`, remove only artifacts unused for this many days
+    /// and artifacts built by a rustc that is no longer installed
+    pub sweep_days: Option<u64>,
+    /// Remove only the output of these profiles (`dev`/`test` map to `debug`, `bench` to `release`)
+    pub profiles: Vec<String>,
+    /// Remove only the output of these target triples; without a profile or `doc`, the whole triple directory goes
+    pub targets: Vec<String>,
+    /// Remove the documentation generated by `cargo doc`
+    pub doc: bool,
+}
+
+impl CargoOptions {
+    /// Whether only the selected profiles, target triples or docs are removed instead of the whole target directory
+    pub fn selects(&self) -> bool {
+        !self.profiles.is_empty() || !self.targets.is_empty() || self.doc
+    }
+}
+
+/// Size of a profile directory (`target/debug`, `target/<triple>/release`, ...) and of what was removed from it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileUsage {
+    pub path: PathBuf,
+    pub size: DirStats,
+    pub removed: DirStats,
+}
+
+/// Paths to remove from one profile directory; just the directory itself when the whole profile (or triple) is unused
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileSweep {
+    pub path: PathBuf,
+    pub stale: Vec<PathBuf>,
+}
+
+/// The Cargo target directory a project actually builds into
+///
+/// Resolved the way `cargo` does when run in `dir`: `CARGO_TARGET_DIR`, then `CARGO_BUILD_TARGET_DIR`,
+/// then `build.target-dir` from `.cargo/config.toml` (searched upwards from `dir`, then `$CARGO_HOME`),
+/// and finally `target/` under the workspace root.
+/// Relative paths in the environment variables are relative to the working directory, as in Cargo, not to the project.
+pub fn target_dir(dir: &Path) -> PathBuf {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| dir.to_path_buf());
+    target_dir_in(dir, &cwd, |var| std::env::var_os(var))
+}
+
+fn target_dir_in(dir: &Path, cwd: &Path, env: impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    for var in ["CARGO_TARGET_DIR", "CARGO_BUILD_TARGET_DIR"] {
+        if let Some(value) = env(var).filter(|value| !value.is_empty()) {
+            return normalize(&cwd.join(value));
+        }
+    }
+    configured_target_dir(dir).unwrap_or_else(|| workspace_root(dir).join("target"))
+}
+
+/// Fold `.` and `..` component by component without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn read_toml(path: &Path) -> Option<toml::Table> {
+    std::fs::read_to_string(path).ok()?.parse().ok()
+}
+
+/// `build.target-dir` from Cargo config files; relative paths are relative to the parent of the `.cargo` directory
+fn configured_target_dir(dir: &Path) -> Option<PathBuf> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+    let config_dirs = dir.ancestors().map(|ancestor| ancestor.join(".cargo")).chain(cargo_home);
+    for config_dir in config_dirs {
+        // 两个文件同时存在时 Cargo 使用不带扩展名的 `config`
+        let Some(config) = ["config", "config.toml"].iter().find_map(|name| read_toml(&config_dir.join(name))) else {
+            continue;
+        };
+        let target = config.get("build").and_then(|build| build.get("target-dir")).and_then(|value| value.as_str());
+        if let Some(target) = target {
+            let base = config_dir.parent().unwrap_or(&config_dir);
+            return Some(normalize(&base.join(target)));
+        }
+    }
+    None
+}
+
+/// Root of the workspace the package belongs to, or the package directory when it isn't in a workspace
+///
+/// An explicit `package.workspace` wins; otherwise it is the nearest manifest above with a `[workspace]` that doesn't `exclude` the package.
+pub fn workspace_root(dir: &Path) -> PathBuf {
+    if let Some(manifest) = read_toml(&dir.join("Cargo.toml")) {
+        if manifest.contains_key("workspace") {
+            return dir.to_path_buf();
+        }
+        let explicit = manifest.get("package").and_then(|package| package.get("workspace")).and_then(|value| value.as_str());
+        if let Some(root) = explicit {
+            return normalize(&dir.join(root));
+        }
+    }
+    for ancestor in dir.ancestors().skip(1) {
+        let Some(workspace) = read_toml(&ancestor.join("Cargo.toml")).and_then(|mut manifest| manifest.remove("workspace")) else {
+            continue;
+        };
+        let excluded = workspace
+            .get("exclude")
+            .and_then(|exclude| exclude.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|path| path.as_str())
+            .any(|path| dir.starts_with(normalize(&ancestor.join(path))));
+        if !excluded {
+            return ancestor.to_path_buf();
+        }
+    }
+    dir.to_path_buf()
+}
+
+/// When a path was last used
+///
+/// Files use the newer of the modification and access times (with relatime, reading a file updates atime at least once a day).
+/// Directories use the newest time among their direct children, or their own mtime when empty.
+/// Subdirectories and `*.json` fingerprints only count mtime: measuring sizes updates directory atimes,
+/// and we read the `*.json` files ourselves while Cargo reads the hash files next to them on every build.
+fn last_used(path: &Path) -> Option<SystemTime> {
+    let time = |path: &Path, metadata: &Metadata| {
+        let counts_access = metadata.is_file() && path.extension().map_or(true, |ext| ext != "json");
+        let accessed = if counts_access { metadata.accessed().ok() } else { None };
+        metadata.modified().ok().max(accessed)
+    };
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return time(path, &metadata);
+    }
+    let children = std::fs::read_dir(path).into_iter().flatten().flatten();
+    children
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?)))
+        .filter_map(|(path, metadata)| time(&path, &metadata))
+        .max()
+        .or_else(|| metadata.modified().ok())
+}
+
+/// Metadata hash Cargo appends to file names, e.g. `1a2b3c4d5e6f7a8b` in `libserde-1a2b3c4d5e6f7a8b.rlib`
+fn unit_hash(name: &str) -> Option<&str> {
+    let stem = name.split('.').next()?;
+    let (_, hash) = stem.rsplit_once('-')?;
+    (hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit())).then_some(hash)
+}
+
+/// rustc version hash recorded in a fingerprint directory (the `rustc` field of its `*.json` files)
+fn fingerprint_rustc(unit: &Path) -> Option<u64> {
+    std::fs::read_dir(unit).ok()?.flatten().find_map(|entry| {
+        let path = entry.path();
+        if path.extension()? != "json" {
+            return None;
+        }
+        let value: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).ok()?).ok()?;
+        value.get("rustc")?.as_u64()
+    })
+}
+
+fn sorted_entries(dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// Profile directories in a target directory: children that hold `.fingerprint`, and the same under target triple directories
+///
+/// Returns `(triple directory, profile directory)`; host profiles have no triple directory.
+pub fn profile_dirs(target: &Path) -> Vec<(Option<PathBuf>, PathBuf)> {
+    let mut profiles = vec![];
+    for child in sorted_entries(target).into_iter().filter(|path| path.is_dir()) {
+        if child.join(".fingerprint").is_dir() {
+            profiles.push((None, child));
+            continue;
+        }
+        for profile in sorted_entries(&child) {
+            if profile.join(".fingerprint").is_dir() {
+                profiles.push((Some(child.clone()), profile));
+            }
+        }
+    }
+    profiles
+}
+
+/// Directory name of a profile's output
+pub fn profile_dir_name(profile: &str) -> &str {
+    match profile {
+        "dev" | "test" => "debug",
+        "bench" => "release",
+        other => other,
+    }
+}
+
+/// Profile directories and the `doc` directory of a target directory, for per-profile size reports
+pub fn breakdown_dirs(target: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![];
+    let mut triples: Vec<Option<PathBuf>> = vec![None];
+    for (triple, profile) in profile_dirs(target) {
+        if !triples.contains(&triple) {
+            triples.push(triple);
+        }
+        dirs.push(profile);
+    }
+    for triple in triples {
+        let doc = triple.as_deref().unwrap_or(target).join("doc");
+        if doc.is_dir() {
+            dirs.push(doc);
+        }
+    }
+    dirs
+}
+
+/// Select the directories to remove for `--profile`, `--target` and `--doc`, matching the `cargo clean` options of the same names
+///
+/// Every profile and doc directory in the target directory is listed (with an empty `stale` when unselected) so each profile's size can be reported;
+/// with only target triples given, each triple directory is a single entry.
+pub fn plan_selection(target: &Path, options: &CargoOptions) -> Vec<ProfileSweep> {
+    let whole_triples = options.profiles.is_empty() && !options.doc;
+    let profiles: Vec<&str> = options.profiles.iter().map(|profile| profile_dir_name(profile)).collect();
+    let selected_triple = |dir: &Path| match dir.parent() {
+        // 未指定目标三元组时只处理宿主平台的输出
+        Some(parent) if parent == target => options.targets.is_empty(),
+        _ => dir
+            .parent()
+            .and_then(|triple| triple.file_name())
+            .is_some_and(|name| options.targets.iter().any(|wanted| OsStr::new(wanted) == name)),
+    };
+
+    let mut plan: Vec<ProfileSweep> = vec![];
+    for dir in breakdown_dirs(target) {
+        let name = dir.file_name().unwrap_or_default();
+        let selected = selected_triple(&dir)
+            && if name == "doc" {
+                options.doc
+            } else {
+                !whole_triples && profiles.iter().any(|profile| OsStr::new(profile) == name)
+            };
+        let stale = if selected { vec![dir.clone()] } else { vec![] };
+        plan.push(ProfileSweep { path: dir, stale });
+    }
+
+    if whole_triples {
+        for triple in options.targets.iter().map(|name| target.join(name)) {
+            if !triple.is_dir() {
+                continue;
+            }
+            let position = plan.iter().position(|profile| profile.path.starts_with(&triple)).unwrap_or(plan.len());
+            plan.retain(|profile| !profile.path.starts_with(&triple));
+            plan.insert(position.min(plan.len()), ProfileSweep { path: triple.clone(), stale: vec![triple] });
+        }
+    }
+    plan
+}
+
+/// Triples from `--target` that don't exist in the target directory
+pub fn missing_targets(target: &Path, options: &CargoOptions) -> Vec<PathBuf> {
+    options.targets.iter().map(|triple| target.join(triple)).filter(|dir| !dir.is_dir()).collect()
+}
+
+/// Find the stale artifacts in one profile directory
+///
+/// Works per compilation unit in `.fingerprint`: when a unit was last used before `cutoff` or was built by a rustc in `uninstalled`,
+/// its fingerprint directory, the directory of the same name in `build/` and the files in `deps/` carrying its hash are removed.
+/// Files in `deps/` that belong to no unit and `incremental/` sessions are judged by age. When everything is stale the whole directory is returned.
+fn sweep_profile(profile: &Path, cutoff: SystemTime, uninstalled: &HashSet<u64>) -> Vec<PathBuf> {
+    let is_stale = |path: &Path| last_used(path).map_or(true, |time| time < cutoff);
+    let mut stale = vec![];
+    let mut stale_hashes = HashSet::new();
+    let mut live_hashes = HashSet::new();
+    let mut all_stale = true;
+
+    for unit in sorted_entries(&profile.join(".fingerprint")) {
+        let Some(name) = unit.file_name().and_then(|name| name.to_str()).map(str::to_string) else {
+            continue;
+        };
+        let foreign = !uninstalled.is_empty() && fingerprint_rustc(&unit).is_some_and(|rustc| uninstalled.contains(&rustc));
+        let hash = unit_hash(&name).map(str::to_string);
+        if foreign || is_stale(&unit) {
+            stale.push(unit);
+            let build = profile.join("build").join(&name);
+            if build.exists() {
+                stale.push(build);
+            }
+            stale_hashes.extend(hash);
+        } else {
+            all_stale = false;
+            live_hashes.extend(hash);
+        }
+    }
+
+    for entry in sorted_entries(&profile.join("deps")) {
+        let hash = entry.file_name().and_then(|name| name.to_str()).and_then(unit_hash);
+        let remove = match hash {
+            Some(hash) if stale_hashes.contains(hash) => true,
+            Some(hash) if live_hashes.contains(hash) => false,
+            _ => is_stale(&entry),
+        };
+        if remove {
+            stale.push(entry);
+        } else {
+            all_stale = false;
+        }
+    }
+
+    for session in sorted_entries(&profile.join("incremental")) {
+        if is_stale(&session) {
+            stale.push(session);
+        } else {
+            all_stale = false;
+        }
+    }
+
+    if all_stale && !stale.is_empty() {
+        vec![profile.to_path_buf()]
+    } else {
+        stale
+    }
+}
+
+/// Find the stale artifacts in a target directory, grouped by profile; a target triple whose profiles are all stale is removed as a whole
+pub fn plan_sweep(target: &Path, cutoff: SystemTime, uninstalled: &HashSet<u64>) -> Vec<ProfileSweep> {
+    let mut plan: Vec<ProfileSweep> = vec![];
+    let mut triples: Vec<(PathBuf, Vec<usize>)> = vec![];
+    for (triple, profile) in profile_dirs(target) {
+        let stale = sweep_profile(&profile, cutoff, uninstalled);
+        if let Some(triple) = triple {
+            match triples.iter_mut().find(|(dir, _)| *dir == triple) {
+                Some((_, members)) => members.push(plan.len()),
+                None => triples.push((triple, vec![plan.len()])),
+            }
+        }
+        plan.push(ProfileSweep { path: profile, stale });
+    }
+
+    // 三元组目录中只有 profile 时才能整体删除
+    for (triple, members) in triples.into_iter().rev() {
+        let profiles_unused = members.iter().all(|&index| plan[index].stale == [plan[index].path.clone()]);
+        let only_profiles = sorted_entries(&triple).len() == members.len();
+        if profiles_unused && only_profiles {
+            let first = members[0];
+            for &index in members.iter().rev() {
+                plan.remove(index);
+            }
+            plan.insert(first, ProfileSweep { path: triple.clone(), stale: vec![triple] });
+        }
+    }
+    plan
+}
+
+/// Measure each profile directory and every path to remove from it, in the same order as `plan`
+pub fn measure_plan(plan: &[ProfileSweep], engine: &SizeEngine) -> Vec<(DirStats, Vec<DirStats>)> {
+    let profiles = plan.iter().map(|profile| (profile.path.clone(), SizeBudget::UNLIMITED)).collect();
+    let profile_sizes = engine.measure_all(profiles, &HardLinkTracker::default());
+    // 待删除的路径单独去重硬链接，否则它们在统计 profile 时已经被计入
+    let stale = plan
+        .iter()
+        .flat_map(|profile| profile.stale.iter().map(|path| (path.clone(), SizeBudget::UNLIMITED)))
+        .collect();
+    let mut stale_sizes = engine.measure_all(stale, &HardLinkTracker::default()).into_iter();
+    profile_sizes
+        .into_iter()
+        .zip(plan)
+        .map(|(size, profile)| (size, stale_sizes.by_ref().take(profile.stale.len()).collect()))
+        .collect()
+}
+
+static INSTALLED_RUSTC: OnceCell<Option<HashSet<String>>> = OnceCell::const_new();
+
+/// `rustc -vV` output of every installed toolchain, queried once per run
+///
+/// Returns `None` when it can't be queried, so the sweep doesn't remove anything by rustc version.
+pub async fn installed_rustc_versions() -> Option<&'static HashSet<String>> {
+    INSTALLED_RUSTC.get_or_init(query_installed_toolchains).await.as_ref()
+}
+
+async fn query_installed_toolchains() -> Option<HashSet<String>> {
+    let verbose_version = |mut command: Command| async move {
+        let output = command.output().await.ok().filter(|output| output.status.success())?;
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let toolchains = match Command::new("rustup").args(["toolchain", "list"]).output().await {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .map(str::to_string)
+            .collect(),
+        _ => vec![],
+    };
+
+    let mut versions = HashSet::new();
+    for toolchain in &toolchains {
+        let mut command = Command::new("rustup");
+        command.args(["run", toolchain, "rustc", "-vV"]);
+        versions.extend(verbose_version(command).await);
+    }
+    // 没有 rustup 时只有 PATH 中的工具链
+    if toolchains.is_empty() {
+        let mut command = Command::new("rustc");
+        command.arg("-vV");
+        versions.extend(verbose_version(command).await);
+    }
+    (!versions.is_empty()).then_some(versions)
+}
+
+/// rustc hashes recorded in the fingerprints of artifacts built by a rustc that is no longer installed
+///
+/// The hash algorithm changes between Cargo versions, so it can't be computed directly. `.rustc_info.json` records the last rustc
+/// run in the target directory (its `rustc -vV` output), and the newest fingerprint written after that record carries this rustc's hash.
+/// Only that hash maps to a known version; any other hash counts as still in use and is only swept by age.
+pub fn uninstalled_rustc_hashes(target: &Path, installed: Option<&HashSet<String>>) -> HashSet<u64> {
+    match (installed, last_build_rustc(target)) {
+        (Some(installed), Some((hash, version))) if !installed.contains(&version) => [hash].into(),
+        _ => HashSet::new(),
+    }
+}
+
+/// Fingerprint hash and `rustc -vV` output of the rustc used by the last build
+fn last_build_rustc(target: &Path) -> Option<(u64, String)> {
+    let info = target.join(".rustc_info.json");
+    let recorded_at = std::fs::metadata(&info).ok()?.modified().ok()?;
+    let value: serde_json::Value = serde_json::from_slice(&std::fs::read(&info).ok()?).ok()?;
+    let version = value
+        .get("outputs")?
+        .as_object()?
+        .values()
+        .filter_map(|output| output.get("stdout")?.as_str())
+        .find(|stdout| stdout.lines().any(|line| line == "binary: rustc"))?
+        .trim()
+        .to_string();
+
+    let (built_at, unit) = profile_dirs(target)
+        .iter()
+        .flat_map(|(_, profile)| sorted_entries(&profile.join(".fingerprint")))
+        .filter_map(|unit| Some((last_used(&unit)?, unit)))
+        .max_by_key(|(time, _)| *time)?;
+    // 记录之后没有再构建过（例如只运行了 `cargo metadata`），最新的指纹可能来自另一个 rustc
+    if built_at < recorded_at {
+        return None;
+    }
+    Some((fingerprint_rustc(&unit)?, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write_aged(path: &Path, content: &str, age_days: u64) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+        let time = SystemTime::now() - Duration::from_secs(age_days * 86400);
+        let times = std::fs::FileTimes::new().set_accessed(time).set_modified(time);
+        std::fs::File::options().write(true).open(path).unwrap().set_times(times).unwrap();
+    }
+
+    /// Fingerprint and `deps/` output of a compilation unit
+    fn unit(profile: &Path, name: &str, rustc: u64, age_days: u64) {
+        let fingerprint = profile.join(".fingerprint").join(name).join("lib.json");
+        write_aged(&fingerprint, &format!(r#"{{"rustc":{}}}"#, rustc), age_days);
+        let (crate_name, hash) = name.rsplit_once('-').unwrap();
+        write_aged(&profile.join("deps").join(format!("lib{}-{}.rlib", crate_name, hash)), "", age_days);
+        write_aged(&profile.join("deps").join(format!("{}-{}.d", crate_name, hash)), "", age_days);
+    }
+
+    #[test]
+    fn test_plan_selection() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path();
+        for profile in ["debug", "release", "wasm32-unknown-unknown/debug", "aarch64-apple-darwin/release"] {
+            std::fs::create_dir_all(target.join(profile).join(".fingerprint")).unwrap();
+        }
+        std::fs::create_dir_all(target.join("doc")).unwrap();
+        let selected = |options: CargoOptions| -> Vec<PathBuf> {
+            plan_selection(target, &options).into_iter().flat_map(|profile| profile.stale).collect()
+        };
+
+        let all = plan_selection(target, &CargoOptions { doc: true, ..Default::default() });
+        assert_eq!(all.len(), 5);
+        let options = CargoOptions { profiles: vec!["dev".to_string()], doc: true, ..Default::default() };
+        assert_eq!(selected(options), vec![target.join("debug"), target.join("doc")]);
+        // 只指定三元组时删除整个三元组目录
+        let options = CargoOptions { targets: vec!["wasm32-unknown-unknown".to_string()], ..Default::default() };
+        assert_eq!(selected(options), vec![target.join("wasm32-unknown-unknown")]);
+        let options = CargoOptions {
+            profiles: vec!["bench".to_string()],
+            targets: vec!["aarch64-apple-darwin".to_string(), "wasm32-unknown-unknown".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(selected(options), vec![target.join("aarch64-apple-darwin/release")]);
+    }
+
+    #[test]
+    fn test_target_dir_from_config_and_workspace() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"tools\"]\n").unwrap();
+        for member in ["crates/core", "tools/gen"] {
+            std::fs::create_dir_all(root.join(member)).unwrap();
+            std::fs::write(root.join(member).join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+        }
+        assert_eq!(workspace_root(&root.join("crates/core")), root);
+        assert_eq!(workspace_root(&root.join("tools/gen")), root.join("tools/gen"));
+        assert_eq!(configured_target_dir(&root.join("crates/core")), None);
+
+        // 相对路径相对于 `.cargo` 所在的目录，离项目最近的配置优先
+        std::fs::create_dir_all(root.join(".cargo")).unwrap();
+        std::fs::write(root.join(".cargo/config.toml"), "[build]\ntarget-dir = \"../shared-target\"\n").unwrap();
+        std::fs::create_dir_all(root.join("tools/gen/.cargo")).unwrap();
+        std::fs::write(root.join("tools/gen/.cargo/config"), "[build]\ntarget-dir = \"out\"\n").unwrap();
+        let shared = root.parent().unwrap().join("shared-target");
+        assert_eq!(configured_target_dir(&root.join("crates/core")), Some(shared));
+        assert_eq!(configured_target_dir(&root.join("tools/gen")), Some(root.join("tools/gen/out")));
+    }
+
+    #[test]
+    fn test_relative_target_dir_env_uses_cwd() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let project = dir.path().join("app");
+        let cwd = dir.path().join("elsewhere/shell");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+
+        let env = |value: &'static str| move |var: &str| (var == "CARGO_BUILD_TARGET_DIR").then(|| OsString::from(value));
+        assert_eq!(target_dir_in(&project, &cwd, env("../out")), dir.path().join("elsewhere/out"));
+        assert_eq!(target_dir_in(&project, &cwd, env("/abs/out")), PathBuf::from("/abs/out"));
+        assert_eq!(target_dir_in(&project, &cwd, env("")), project.join("target"));
+    }
+
+    #[test]
+    fn test_unit_hash() {
+        assert_eq!(unit_hash("libserde-1a2b3c4d5e6f7a8b.rlib"), Some("1a2b3c4d5e6f7a8b"));
+        assert_eq!(unit_hash("rs_clean-0123456789abcdef"), Some("0123456789abcdef"));
+        assert_eq!(unit_hash("rs_clean.d"), None);
+    }
+
+    #[test]
+    fn test_plan_sweep() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path();
+        let debug = target.join("debug");
+        unit(&debug, "fresh-0000000000000001", 7, 0);
+        unit(&debug, "old-0000000000000002", 7, 40);
+        unit(&debug, "nightly-0000000000000003", 9, 0);
+        write_aged(&debug.join("build/old-0000000000000002/output"), "", 40);
+        write_aged(&debug.join("incremental/fresh-abc/s-1/query-cache.bin"), "", 0);
+        // 整个 release 和 wasm 三元组都很久没有用过
+        let release = target.join("release");
+        unit(&release, "fresh-0000000000000004", 7, 60);
+        let wasm = target.join("wasm32-unknown-unknown");
+        unit(&wasm.join("debug"), "fresh-0000000000000005", 7, 60);
+        unit(&wasm.join("release"), "fresh-0000000000000006", 7, 60);
+
+        let cutoff = SystemTime::now() - Duration::from_secs(30 * 86400);
+        let uninstalled: HashSet<u64> = [9].into();
+        let plan = plan_sweep(target, cutoff, &uninstalled);
+        let stale: Vec<_> = plan.iter().map(|profile| (profile.path.clone(), profile.stale.clone())).collect();
+        assert_eq!(
+            stale,
+            vec![
+                (
+                    debug.clone(),
+                    vec![
+                        debug.join(".fingerprint/nightly-0000000000000003"),
+                        debug.join(".fingerprint/old-0000000000000002"),
+                        debug.join("build/old-0000000000000002"),
+                        debug.join("deps/libnightly-0000000000000003.rlib"),
+                        debug.join("deps/libold-0000000000000002.rlib"),
+                        debug.join("deps/nightly-0000000000000003.d"),
+                        debug.join("deps/old-0000000000000002.d"),
+                    ]
+                ),
+                (release.clone(), vec![release]),
+                (wasm.clone(), vec![wasm]),
+            ]
+        );
+
+        // 无法确定 rustc 版本的单元视为仍在使用
+        let plan = plan_sweep(target, cutoff, &HashSet::new());
+        assert!(!plan[0].stale.contains(&debug.join(".fingerprint/nightly-0000000000000003")));
+    }
+
+    #[test]
+    fn test_uninstalled_rustc_hashes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path();
+        let nightly = "rustc 1.90.0-nightly (abcdef 2025-06-01)\nbinary: rustc\nrelease: 1.90.0-nightly";
+        let stable = "rustc 1.80.0 (051478957 2024-07-21)\nbinary: rustc\nrelease: 1.80.0";
+        let info = serde_json::json!({ "outputs": { "1": { "success": true, "stdout": format!("{}\n", nightly) } } });
+        write_aged(&target.join(".rustc_info.json"), &info.to_string(), 1);
+        unit(&target.join("debug"), "old-0000000000000001", 7, 40);
+        unit(&target.join("debug"), "new-0000000000000002", 9, 0);
+
+        let installed: HashSet<String> = [stable.to_string()].into();
+        assert_eq!(uninstalled_rustc_hashes(target, Some(&installed)), [9].into());
+        let installed: HashSet<String> = [stable.to_string(), nightly.to_string()].into();
+        assert!(uninstalled_rustc_hashes(target, Some(&installed)).is_empty());
+        assert!(uninstalled_rustc_hashes(target, None).is_empty());
+
+        // 记录之后没有构建过时无法确定最新的指纹属于哪个 rustc
+        write_aged(&target.join(".rustc_info.json"), &info.to_string(), 0);
+        let installed: HashSet<String> = [stable.to_string()].into();
+        assert!(uninstalled_rustc_hashes(target, Some(&installed)).is_empty());
+    }
+}
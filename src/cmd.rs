@@ -1,21 +1,32 @@
 use std::io;
-use std::path::Path;
-use tokio::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize, Serializer};
 use tokio::process::Command;
 use thiserror::Error;
+use crate::cargo::{self, CargoOptions, ProfileSweep, ProfileUsage};
+use crate::constant::{get_artifact_map, DEFAULT_EXCLUDE_DIRS, PYTHON_COMPILED_EXTENSIONS, PYTHON_NESTED_CACHE_DIRS};
+use crate::delete::Deleter;
+use crate::go::{self, GoOptions};
+use crate::ignore::{IgnoreSet, MATCH_OPTIONS};
+use crate::node::{self, NodeOptions};
+use crate::os_path;
+use crate::size::{DirStats, SizeEngine};
+use std::ffi::OsStr;
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
 
 #[derive(Error, Debug)]
 pub enum CleanError {
-    #[error("Failed to execute command '{command}' in '{path}': {source}")]
+    #[error("Failed to execute command '{command}' in '{}': {source}", os_path::escape(path))]
     CommandExecutionFailed {
         command: String,
-        path: String,
+        path: PathBuf,
         #[source]
         source: io::Error,
     },
-    #[error("Failed to remove directory '{path}': {source}")]
+    #[error("Failed to remove directory '{}': {source}", os_path::escape(path))]
     DirectoryRemovalFailed {
-        path: String,
+        path: PathBuf,
         #[source]
         source: io::Error,
     },
@@ -50,6 +61,12 @@ impl CommandType {
     }
 }
 
+impl Serialize for CommandType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 impl From<&str> for CommandType {
     fn from(s: &str) -> Self {
         match s {
@@ -66,9 +83,77 @@ impl From<&str> for CommandType {
     }
 }
 
+/// Result of a cleanup: the paths we removed ourselves, or an external cleanup command that did the work (with the command that ran)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CleanOutcome {
+    Removed(Vec<PathBuf>),
+    External(String),
+    /// Some artifacts could not be removed; the rest were cleaned as usual
+    Partial {
+        removed: Vec<PathBuf>,
+        /// Artifacts that could not be removed, with the reason
+        failed: Vec<(PathBuf, String)>,
+    },
+    /// The project was left alone, with the reason
+    Kept(String),
+    /// Cargo sweep or profile selection: only part of the target directory was removed
+    Swept {
+        /// Removed paths with their statistics from before removal
+        removed: Vec<(PathBuf, DirStats)>,
+        failed: Vec<(PathBuf, String)>,
+        profiles: Vec<ProfileUsage>,
+    },
+}
+
 pub struct Cmd {
     pub command_type: CommandType,
     pub related_files: Vec<&'static str>,
+    /// Directory names not entered when searching for build artifacts recursively
+    pub exclude_dirs: Vec<String>,
+    pub python: PythonOptions,
+    pub node: NodeOptions,
+    pub go: GoOptions,
+    pub cargo: CargoOptions,
+}
+
+/// Why a project with an excluded artifact is skipped as a whole
+fn excluded_reason(dir: &Path, artifact: &Path) -> String {
+    format!("{} is excluded by .rs_cleanignore or --exclude", artifact.strip_prefix(dir).unwrap_or(artifact).display())
+}
+
+/// Cleaning options for Python projects (`[python]` in the config file)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PythonOptions {
+    /// Also remove virtual environments in the project directory
+    pub remove_venvs: bool,
+}
+
+/// Whether a directory is a Python virtual environment (venv/virtualenv or conda)
+pub fn is_virtualenv(dir: &Path) -> bool {
+    dir.join("pyvenv.cfg").is_file() || dir.join("conda-meta").is_dir()
+}
+
+/// Interpreter version of a virtual environment
+///
+/// For venv it is `version` in `pyvenv.cfg` (`version_info` for virtualenv);
+/// for conda environments it is parsed from the `conda-meta/python-<version>-<build>.json` file name.
+pub fn python_env_version(dir: &Path) -> Option<String> {
+    if let Ok(cfg) = std::fs::read_to_string(dir.join("pyvenv.cfg")) {
+        let value = |wanted: &str| {
+            cfg.lines().find_map(|line| {
+                let (key, value) = line.split_once('=')?;
+                (key.trim() == wanted).then(|| value.trim().to_string())
+            })
+        };
+        return value("version").or_else(|| value("version_info"));
+    }
+    std::fs::read_dir(dir.join("conda-meta")).ok()?.flatten().find_map(|entry| {
+        let name = entry.file_name();
+        let build = name.to_str()?.strip_prefix("python-")?.strip_suffix(".json")?;
+        let version = build.split('-').next()?;
+        version.starts_with(|c: char| c.is_ascii_digit()).then(|| version.to_string())
+    })
 }
 
 impl Cmd {
@@ -76,94 +161,363 @@ impl Cmd {
         Self {
             command_type,
             related_files,
+            exclude_dirs: DEFAULT_EXCLUDE_DIRS.map(String::from).to_vec(),
+            python: PythonOptions::default(),
+            node: NodeOptions::default(),
+            go: GoOptions::default(),
+            cargo: CargoOptions::default(),
         }
     }
 
-    pub async fn run_clean(&self, dir: &Path) -> Result<(), CleanError> {
+    pub fn python(mut self, options: PythonOptions) -> Self {
+        self.python = options;
+        self
+    }
+
+    pub fn node(mut self, options: NodeOptions) -> Self {
+        self.node = options;
+        self
+    }
+
+    pub fn go(mut self, options: GoOptions) -> Self {
+        self.go = options;
+        self
+    }
+
+    pub fn cargo(mut self, options: CargoOptions) -> Self {
+        self.cargo = options;
+        self
+    }
+
+    /// Replace the directory names skipped when searching for build artifacts recursively (same as `--exclude-dir`)
+    pub fn exclude_dirs(mut self, names: Vec<String>) -> Self {
+        self.exclude_dirs = names;
+        self
+    }
+
+    /// Clean the build artifacts in `dir`; artifacts excluded by `.rs_cleanignore` are kept
+    pub async fn run_clean(&self, dir: &Path, deleter: &Deleter, engine: &SizeEngine, ignores: &IgnoreSet) -> Result<CleanOutcome, CleanError> {
         match self.command_type {
-            CommandType::NodeJs => self.clean_nodejs_project(dir).await,
-            CommandType::Python => self.clean_python_project(dir).await,
+            CommandType::NodeJs => self.clean_nodejs_project(dir, deleter, ignores).await,
+            CommandType::Python => self.clean_python_project(dir, deleter, ignores).await,
+            CommandType::Go if self.go.remove_outputs => self.clean_go_project(dir, deleter, ignores).await,
+            CommandType::Cargo if self.cargo.selects() => self.clean_cargo_selection(dir, deleter, engine, ignores).await,
+            CommandType::Cargo if self.cargo.sweep_days.is_some() => self.sweep_cargo_project(dir, deleter, engine, ignores).await,
             _ => {
-                let cmd_name = self.command_type.as_str();
-                let mut command = Command::new(cmd_name);
+                // 外部清理命令无法只清理一部分产物，有任何产物被排除时跳过整个项目
+                let declared = self.artifact_paths(dir, &IgnoreSet::default());
+                if let Some(ignored) = declared.iter().find(|path| ignores.is_ignored(path, path.is_dir())) {
+                    return Ok(CleanOutcome::Kept(excluded_reason(dir, ignored)));
+                }
+                self.run_external_clean(dir).await
+            }
+        }
+    }
+
+    /// Run the ecosystem's own cleanup command in `dir` (`cargo clean`, `go clean`, ...)
+    async fn run_external_clean(&self, dir: &Path) -> Result<CleanOutcome, CleanError> {
+        let cmd_name = self.command_type.as_str();
+        let mut command = Command::new(cmd_name);
+
+        #[cfg(target_os = "windows")]
+        {
+            if self.command_type == CommandType::Flutter {
+                command = Command::new("flutter.bat");
+            }
+        }
+        command.arg("clean");
+        command.current_dir(dir);
+
+        let command_line = format!("{} clean", cmd_name);
+        command.output().await.map(|_| CleanOutcome::External(command_line.clone())).map_err(|source| CleanError::CommandExecutionFailed {
+            command: command_line,
+            path: dir.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Whether this ecosystem declares artifact paths that can be measured directly
+    pub fn declares_artifacts(&self) -> bool {
+        (self.command_type == CommandType::Go && self.go.remove_outputs)
+            || get_artifact_map()
+                .get(&self.command_type)
+                .is_some_and(|patterns| !patterns.is_empty())
+    }
+
+    /// Existing artifact paths of the project in `dir`, with glob patterns expanded
+    /// and paths excluded by `.rs_cleanignore` removed
+    pub fn artifact_paths(&self, dir: &Path, ignores: &IgnoreSet) -> Vec<PathBuf> {
+        let mut paths = match self.command_type {
+            // 输出目录可能被配置到项目之外，或位于工作区根目录
+            CommandType::Cargo if self.cargo.selects() => {
+                cargo::plan_selection(&cargo::target_dir(dir), &self.cargo).into_iter().flat_map(|profile| profile.stale).collect()
+            }
+            CommandType::Cargo => {
+                let target = cargo::target_dir(dir);
+                if target.symlink_metadata().is_ok() {
+                    vec![target]
+                } else {
+                    vec![]
+                }
+            }
+            _ => self.declared_paths(dir),
+        };
+        if self.command_type == CommandType::NodeJs {
+            paths.extend(node::package_manager_artifacts(dir));
+            paths.extend(node::framework_outputs(dir));
+            // 工作区根目录一并清理所有成员包，成员包不再单独清理
+            if let Some(mut workspace) = node::Workspace::detect(dir, &self.exclude_dirs) {
+                // 被 `.rs_cleanignore` 或 `--exclude` 排除的成员包（包括位于被排除目录中的）保持原样
+                workspace.members.retain(|member| !ignores.is_ignored(member, true));
+                for member in &workspace.members {
+                    paths.extend(self.declared_paths(member));
+                    paths.extend(node::framework_outputs(member));
+                }
+                paths.extend(workspace.tool_caches());
+            }
+            // 去掉重复的路径以及位于其它产物之内的路径（例如 `node_modules/.cache/turbo`）
+            let mut unique: Vec<PathBuf> = vec![];
+            for path in paths {
+                if !unique.iter().any(|listed| path.starts_with(listed)) {
+                    unique.retain(|listed| !listed.starts_with(&path));
+                    unique.push(path);
+                }
+            }
+            paths = unique;
+        }
+        if self.command_type == CommandType::Go && self.go.remove_outputs {
+            paths.extend(go::build_outputs(dir));
+        }
+        if self.command_type == CommandType::Python {
+            if self.python.remove_venvs {
+                for env in self.virtualenvs(dir) {
+                    if !paths.contains(&env) {
+                        paths.push(env);
+                    }
+                }
+            }
+            let nested = self.nested_python_artifacts(dir, &paths, ignores);
+            paths.extend(nested);
+        }
+        if !ignores.is_empty() {
+            paths.retain(|path| !ignores.is_ignored(path, path.is_dir()));
+        }
+        paths
+    }
+
+    /// The declared artifact paths of the ecosystem that actually exist, with wildcards expanded
+    fn declared_paths(&self, dir: &Path) -> Vec<PathBuf> {
+        let patterns = get_artifact_map()
+            .get(&self.command_type)
+            .map(|v| v.as_slice())
+            .unwrap_or_default();
+
+        let mut paths = vec![];
+        for pattern in patterns {
+            // 通配模式（例如 "*.egg-info"）只匹配项目根目录下的名字；逐个比较目录项，
+            // 不把项目路径本身拼进模式，避免路径中的 `[`、`*` 或非 UTF-8 字节干扰匹配
+            if pattern.contains('*') {
+                let Ok(glob) = glob::Pattern::new(pattern) else {
+                    continue;
+                };
+                if let Ok(entries) = std::fs::read_dir(dir) {
+                    let mut matched: Vec<_> = entries
+                        .flatten()
+                        .filter(|entry| glob.matches_with(&os_path::glob_text(&entry.file_name()), MATCH_OPTIONS))
+                        .map(|entry| entry.path())
+                        .filter(|path| path.is_dir())
+                        .collect();
+                    matched.sort();
+                    paths.extend(matched);
+                }
+            } else {
+                let path = dir.join(pattern);
+                // 不跟随符号链接判断是否存在，悬空的链接同样需要清理
+                if std::fs::symlink_metadata(&path).is_ok() && !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
 
-                #[cfg(target_os = "windows")]
-                {
-                    if self.command_type == CommandType::Flutter {
-                        command = Command::new("flutter.bat");
+    /// Virtual environments in the project directory, whatever their name (`venv`, `.venv`, `env`, `.env-3.12`, ...)
+    fn virtualenvs(&self, dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return vec![];
+        };
+        let mut envs: Vec<_> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            .map(|entry| entry.path())
+            .filter(|path| is_virtualenv(path))
+            .collect();
+        envs.sort();
+        envs
+    }
+
+    /// `__pycache__` and `.ipynb_checkpoints` in the project's package directories at any depth, plus stray `*.pyc`/`*.pyo` files
+    ///
+    /// Hidden directories, virtual environments, excluded directories, listed artifacts and nested Python projects (cleaned on their own) aren't entered.
+    fn nested_python_artifacts(&self, dir: &Path, listed: &[PathBuf], ignores: &IgnoreSet) -> Vec<PathBuf> {
+        let mut found = vec![];
+        let mut walker = WalkDir::new(dir).min_depth(1).into_iter();
+        while let Some(entry) = walker.next() {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let (name, path) = (entry.file_name(), entry.path());
+            if entry.file_type().is_dir() {
+                if PYTHON_NESTED_CACHE_DIRS.iter().any(|cache| OsStr::new(cache) == name) {
+                    if !listed.iter().any(|p| p == path) {
+                        found.push(path.to_path_buf());
                     }
+                    walker.skip_current_dir();
+                    continue;
+                }
+                let prune = name.as_encoded_bytes().starts_with(b".")
+                    || self.exclude_dirs.iter().any(|excluded| OsStr::new(excluded) == name)
+                    || listed.iter().any(|p| p == path)
+                    || is_virtualenv(path)
+                    || self.related_files.iter().any(|file| path.join(file).exists())
+                    || ignores.is_ignored(path, true);
+                if prune {
+                    walker.skip_current_dir();
                 }
-                command.arg("clean");
-                command.current_dir(dir);
-
-                command.output().await.map(|_| ()).map_err(|source| CleanError::CommandExecutionFailed {
-                    command: format!("{} clean", cmd_name),
-                    path: dir.display().to_string(),
-                    source,
-                })
+            } else if entry.file_type().is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| PYTHON_COMPILED_EXTENSIONS.iter().any(|compiled| OsStr::new(compiled) == ext))
+            {
+                found.push(path.to_path_buf());
             }
         }
+        found
     }
 
-    async fn clean_nodejs_project(&self, dir: &Path) -> Result<(), CleanError> {
-        let common_node_dirs = vec![
-            "node_modules",
-            "dist",
-            "build",
-            ".next", // Next.js build output
-            "out",   // Common build output or Parcel
-            "coverage", // Test coverage reports
-            ".cache", // General cache directory
-        ];
+    async fn clean_nodejs_project(&self, dir: &Path, deleter: &Deleter, ignores: &IgnoreSet) -> Result<CleanOutcome, CleanError> {
+        self.remove_artifacts(dir, deleter, ignores).await
+    }
 
-        for sub_dir_name in common_node_dirs {
-            let path_to_clean = dir.join(sub_dir_name);
-            self.remove_dir_if_exists(&path_to_clean).await?;
+    async fn remove_artifacts(&self, dir: &Path, deleter: &Deleter, ignores: &IgnoreSet) -> Result<CleanOutcome, CleanError> {
+        let mut removed = vec![];
+        let mut failed = vec![];
+        for path_to_clean in self.artifact_paths(dir, ignores) {
+            // 单个产物失败（例如属于其他用户的文件）不影响其余产物的清理
+            match self.remove_dir_if_exists(&path_to_clean, dir, deleter).await {
+                Ok(true) => removed.push(path_to_clean),
+                Ok(false) => {}
+                Err(e) => failed.push((path_to_clean, e.to_string())),
+            }
         }
-        Ok(())
+        if failed.is_empty() {
+            Ok(CleanOutcome::Removed(removed))
+        } else {
+            Ok(CleanOutcome::Partial { removed, failed })
+        }
+    }
+
+    async fn remove_dir_if_exists(&self, path: &Path, project: &Path, deleter: &Deleter) -> Result<bool, CleanError> {
+        deleter.remove(path, project).await.map_err(|source| CleanError::DirectoryRemovalFailed {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// `go clean` only removes object files in the module directory and frees almost nothing; we remove `bin/` and `dist/` ourselves
+    async fn clean_go_project(&self, dir: &Path, deleter: &Deleter, ignores: &IgnoreSet) -> Result<CleanOutcome, CleanError> {
+        self.run_external_clean(dir).await?;
+        self.remove_artifacts(dir, deleter, ignores).await
     }
 
-    async fn remove_dir_if_exists(&self, path: &Path) -> Result<(), CleanError> {
-        if path.exists() {
-            fs::remove_dir_all(path).await.map_err(|source| CleanError::DirectoryRemovalFailed {
-                path: path.display().to_string(),
-                source,
-            })?;
+    /// Sweep mode: instead of `cargo clean`, remove only stale artifacts and report per profile
+    async fn sweep_cargo_project(&self, dir: &Path, deleter: &Deleter, engine: &SizeEngine, ignores: &IgnoreSet) -> Result<CleanOutcome, CleanError> {
+        let target = cargo::target_dir(dir);
+        if ignores.is_ignored(&target, true) {
+            return Ok(CleanOutcome::Kept(excluded_reason(dir, &target)));
         }
-        Ok(())
+        if !target.is_dir() {
+            return Ok(CleanOutcome::Removed(vec![]));
+        }
+        let days = self.cargo.sweep_days.unwrap_or_default();
+        let cutoff = SystemTime::now()
+            .checked_sub(Duration::from_secs(days.saturating_mul(24 * 60 * 60)))
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let installed = cargo::installed_rustc_versions().await;
+        let engine = engine.clone();
+        let (plan, sizes) = tokio::task::spawn_blocking(move || {
+            let uninstalled = cargo::uninstalled_rustc_hashes(&target, installed);
+            let plan = cargo::plan_sweep(&target, cutoff, &uninstalled);
+            let sizes = cargo::measure_plan(&plan, &engine);
+            (plan, sizes)
+        })
+        .await
+        .map_err(io::Error::other)?;
+        self.remove_profile_plan(dir, plan, sizes, deleter, ignores).await
     }
 
-    async fn clean_python_project(&self, dir: &Path) -> Result<(), CleanError> {
-        let common_python_dirs = vec![
-            "__pycache__",
-            "build",
-            "dist",
-            ".eggs",
-            "*.egg-info", // This is a glob pattern, needs special handling or direct removal if possible
-            ".pytest_cache",
-            "htmlcov",
-            ".mypy_cache",
-            "venv", // Common virtual environment name
-            ".venv", // Common virtual environment name
-        ];
-
-        for sub_dir_name in common_python_dirs {
-            // For glob patterns like "*.egg-info", we need to list and remove
-            if sub_dir_name.contains('*') {
-                let pattern = dir.join(sub_dir_name).to_string_lossy().into_owned();
-                for entry in glob::glob(&pattern).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))? {
-                    if let Ok(path) = entry {
-                        if path.is_dir() {
-                            self.remove_dir_if_exists(&path).await?;
-                        }
+    /// Remove only the directories selected by `--profile`, `--target` and `--doc`, reporting per profile
+    async fn clean_cargo_selection(&self, dir: &Path, deleter: &Deleter, engine: &SizeEngine, ignores: &IgnoreSet) -> Result<CleanOutcome, CleanError> {
+        let target = cargo::target_dir(dir);
+        if ignores.is_ignored(&target, true) {
+            return Ok(CleanOutcome::Kept(excluded_reason(dir, &target)));
+        }
+        if !target.is_dir() {
+            return Ok(CleanOutcome::Removed(vec![]));
+        }
+        let options = self.cargo.clone();
+        let engine = engine.clone();
+        let (plan, sizes) = tokio::task::spawn_blocking(move || {
+            let plan = cargo::plan_selection(&target, &options);
+            let sizes = cargo::measure_plan(&plan, &engine);
+            (plan, sizes)
+        })
+        .await
+        .map_err(io::Error::other)?;
+        let mut outcome = self.remove_profile_plan(dir, plan, sizes, deleter, ignores).await?;
+        // 拼错的三元组不应看起来像是清理成功
+        if let CleanOutcome::Swept { failed, .. } = &mut outcome {
+            for triple in cargo::missing_targets(&cargo::target_dir(dir), &self.cargo) {
+                failed.push((triple, "no such target triple".to_string()));
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Remove the paths in the plan (except those excluded by `.rs_cleanignore`), returning each profile's size and what was removed from it
+    async fn remove_profile_plan(
+        &self,
+        dir: &Path,
+        plan: Vec<ProfileSweep>,
+        sizes: Vec<(DirStats, Vec<DirStats>)>,
+        deleter: &Deleter,
+        ignores: &IgnoreSet,
+    ) -> Result<CleanOutcome, CleanError> {
+        let mut removed = vec![];
+        let mut failed = vec![];
+        let mut profiles = vec![];
+        for (profile, (size, stale_sizes)) in plan.into_iter().zip(sizes) {
+            let mut usage = ProfileUsage { path: profile.path, size, removed: DirStats::default() };
+            for (path, stats) in profile.stale.into_iter().zip(stale_sizes) {
+                if ignores.is_ignored(&path, path.is_dir()) {
+                    continue;
+                }
+                match self.remove_dir_if_exists(&path, dir, deleter).await {
+                    Ok(true) => {
+                        usage.removed.merge(&stats);
+                        removed.push((path, stats));
                     }
+                    Ok(false) => {}
+                    Err(e) => failed.push((path, e.to_string())),
                 }
-            } else {
-                let path_to_clean = dir.join(sub_dir_name);
-                self.remove_dir_if_exists(&path_to_clean).await?;
             }
+            profiles.push(usage);
         }
-        Ok(())
+        Ok(CleanOutcome::Swept { removed, failed, profiles })
+    }
+
+    async fn clean_python_project(&self, dir: &Path, deleter: &Deleter, ignores: &IgnoreSet) -> Result<CleanOutcome, CleanError> {
+        self.remove_artifacts(dir, deleter, ignores).await
     }
 }
 
@@ -194,4 +548,191 @@ mod tests {
         assert!(!cmd_list.is_empty());
         assert!(cmd_list.iter().any(|cmd| cmd.command_type == CommandType::Cargo));
     }
+
+    #[test]
+    fn test_artifact_paths_expands_globs() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("__pycache__")).unwrap();
+        std::fs::create_dir(dir.path().join("pkg.egg-info")).unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+
+        let cmd = Cmd::new(CommandType::Python, vec!["pyproject.toml"]);
+        let mut paths = cmd.artifact_paths(dir.path(), &IgnoreSet::default());
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![dir.path().join("__pycache__"), dir.path().join("pkg.egg-info")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_python_nested_artifacts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        for sub in [
+            "__pycache__",
+            "pkg/__pycache__",
+            "pkg/sub/__pycache__",
+            "notebooks/.ipynb_checkpoints",
+            ".tox/py312/lib/__pycache__",
+            "env/lib/__pycache__",
+            "vendor/lib/__pycache__",
+            "plugins/other/__pycache__",
+        ] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        std::fs::write(root.join("pyproject.toml"), "").unwrap();
+        std::fs::write(root.join("legacy.pyc"), "x").unwrap();
+        std::fs::write(root.join("pkg/sub/mod.pyo"), "x").unwrap();
+        std::fs::write(root.join("pkg/sub/mod.py"), "x").unwrap();
+        // 虚拟环境和嵌套的项目各自处理
+        std::fs::write(root.join("env/pyvenv.cfg"), "").unwrap();
+        std::fs::write(root.join("plugins/other/pyproject.toml"), "").unwrap();
+
+        let cmd = Cmd::new(CommandType::Python, vec!["pyproject.toml"]);
+        let mut paths = cmd.artifact_paths(root, &IgnoreSet::default());
+        paths.sort();
+        let mut expected: Vec<_> = [
+            "__pycache__",
+            "pkg/__pycache__",
+            "pkg/sub/__pycache__",
+            "notebooks/.ipynb_checkpoints",
+            ".tox",
+            "legacy.pyc",
+            "pkg/sub/mod.pyo",
+        ]
+        .iter()
+        .map(|p| root.join(p))
+        .collect();
+        expected.sort();
+        assert_eq!(paths, expected);
+
+        let deleter = Deleter::new(2);
+        cmd.run_clean(root, &deleter, &SizeEngine::new(1), &IgnoreSet::default()).await.unwrap();
+        assert!(deleter.finish().await.is_empty());
+        assert!(!root.join("pkg/sub/mod.pyo").exists());
+        assert!(root.join("pkg/sub/mod.py").exists());
+        assert!(root.join("env/lib/__pycache__").exists());
+        assert!(root.join("plugins/other/__pycache__").exists());
+    }
+
+    #[test]
+    fn test_virtualenvs_are_opt_in() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("setup.py"), "").unwrap();
+        std::fs::create_dir_all(root.join(".venv")).unwrap();
+        std::fs::write(root.join(".venv/pyvenv.cfg"), "home = /usr/bin\nversion = 3.12.1\n").unwrap();
+        std::fs::create_dir_all(root.join("env/conda-meta")).unwrap();
+        std::fs::write(root.join("env/conda-meta/python-dateutil-2.8.2-pyhd_0.json"), "{}").unwrap();
+        std::fs::write(root.join("env/conda-meta/python-3.11.5-h955ad1f_0.json"), "{}").unwrap();
+        // 只是名字叫 venv 的普通目录不是虚拟环境
+        std::fs::create_dir_all(root.join("venv")).unwrap();
+
+        let cmd = Cmd::new(CommandType::Python, vec!["setup.py"]);
+        assert!(cmd.artifact_paths(root, &IgnoreSet::default()).is_empty());
+
+        let cmd = cmd.python(PythonOptions { remove_venvs: true });
+        let paths = cmd.artifact_paths(root, &IgnoreSet::default());
+        assert_eq!(paths, vec![root.join(".venv"), root.join("env")]);
+        assert_eq!(python_env_version(&root.join(".venv")).as_deref(), Some("3.12.1"));
+        assert_eq!(python_env_version(&root.join("env")).as_deref(), Some("3.11.5"));
+        assert_eq!(python_env_version(&root.join("venv")), None);
+    }
+
+    #[tokio::test]
+    async fn test_run_clean_reports_removed_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        std::fs::create_dir_all(dir.path().join("dist")).unwrap();
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        std::fs::write(dir.path().join(".rs_cleanignore"), "dist/\n").unwrap();
+
+        let mut ignores = IgnoreSet::default();
+        ignores.push(crate::ignore::IgnoreFile::load(dir.path()).unwrap());
+        let cmd = Cmd::new(CommandType::NodeJs, vec!["package.json"]);
+        let deleter = Deleter::new(2);
+        let outcome = cmd.run_clean(dir.path(), &deleter, &SizeEngine::new(1), &ignores).await.unwrap();
+        assert_eq!(outcome, CleanOutcome::Removed(vec![dir.path().join("node_modules")]));
+        assert!(dir.path().join("dist").exists());
+        assert!(!dir.path().join("node_modules").exists());
+        assert!(deleter.finish().await.is_empty());
+        assert!(dir.path().join("package.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_cargo_selection_respects_ignored_target() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("target/debug/.fingerprint")).unwrap();
+        std::fs::create_dir_all(dir.path().join("target/doc")).unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        std::fs::write(dir.path().join(".rs_cleanignore"), "/target/\n").unwrap();
+
+        let mut ignores = IgnoreSet::default();
+        ignores.push(crate::ignore::IgnoreFile::load(dir.path()).unwrap());
+        let options = CargoOptions { profiles: vec!["dev".to_string()], doc: true, ..Default::default() };
+        let cmd = Cmd::new(CommandType::Cargo, vec!["Cargo.toml"]).cargo(options);
+        let deleter = Deleter::new(1);
+        let outcome = cmd.run_clean(dir.path(), &deleter, &SizeEngine::new(1), &ignores).await.unwrap();
+        assert_eq!(outcome, CleanOutcome::Kept("target is excluded by .rs_cleanignore or --exclude".to_string()));
+        assert!(deleter.finish().await.is_empty());
+        assert!(dir.path().join("target/debug").exists());
+        assert!(dir.path().join("target/doc").exists());
+
+        let options = CargoOptions { targets: vec!["wasm32-unknown-unkown".to_string()], ..Default::default() };
+        let cmd = Cmd::new(CommandType::Cargo, vec!["Cargo.toml"]).cargo(options);
+        let outcome = cmd.run_clean(dir.path(), &deleter, &SizeEngine::new(1), &IgnoreSet::default()).await.unwrap();
+        let CleanOutcome::Swept { removed, failed, .. } = outcome else {
+            panic!("expected a profile selection, got {:?}", outcome);
+        };
+        assert!(removed.is_empty());
+        assert_eq!(failed, vec![(dir.path().join("target/wasm32-unknown-unkown"), "no such target triple".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_run_clean_continues_after_failed_artifact() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        std::fs::create_dir_all(dir.path().join("dist")).unwrap();
+        std::fs::write(dir.path().join("package.json"), r#"{"devDependencies":{"vite":"^5"}}"#).unwrap();
+        // 隔离区路径是普通文件，每个产物都无法移入
+        let blocked = dir.path().join("not-a-dir");
+        std::fs::write(&blocked, "").unwrap();
+
+        let quarantine = crate::quarantine::Quarantine::new(blocked, std::time::Duration::from_secs(60));
+        let deleter = Deleter::with_mode(2, crate::delete::DeleteMode::Quarantine(quarantine));
+        let cmd = Cmd::new(CommandType::NodeJs, vec!["package.json"]);
+        let outcome = cmd.run_clean(dir.path(), &deleter, &SizeEngine::new(1), &IgnoreSet::default()).await.unwrap();
+
+        let CleanOutcome::Partial { removed, failed } = outcome else {
+            panic!("expected a partial cleanup, got {:?}", outcome);
+        };
+        assert!(removed.is_empty());
+        let failed: Vec<_> = failed.into_iter().map(|(path, _)| path).collect();
+        assert!(failed.contains(&dir.path().join("node_modules")));
+        assert!(failed.contains(&dir.path().join("dist")));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_non_utf8_project_paths() {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = tempfile::TempDir::new().unwrap();
+        // 非 UTF-8 字节以及 glob 元字符都出现在项目路径中
+        let project = dir.path().join(std::ffi::OsStr::from_bytes(b"proj[\xFF]"));
+        let egg_info = project.join(std::ffi::OsStr::from_bytes(b"caf\xE9.egg-info"));
+        std::fs::create_dir_all(&egg_info).unwrap();
+        std::fs::create_dir_all(project.join("__pycache__")).unwrap();
+        std::fs::write(project.join("pyproject.toml"), "").unwrap();
+
+        let cmd = Cmd::new(CommandType::Python, vec!["pyproject.toml"]);
+        let mut paths = cmd.artifact_paths(&project, &IgnoreSet::default());
+        paths.sort();
+        assert_eq!(paths, vec![project.join("__pycache__"), egg_info.clone()]);
+
+        let deleter = Deleter::new(1);
+        cmd.run_clean(&project, &deleter, &SizeEngine::new(1), &IgnoreSet::default()).await.unwrap();
+        assert!(deleter.finish().await.is_empty());
+        assert!(!egg_info.exists());
+    }
 }
@@ -2,29 +2,187 @@ use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use clap::Parser;
-use crate::utils::{validate_and_sanitize_path, validate_exclude_dir_name};
-use crate::constant::{DEFAULT_MAX_DIRECTORY_DEPTH, DEFAULT_MAX_FILES_PER_PROJECT};
+use crate::safety::SafetyPolicy;
+use crate::utils::{validate_exclude_dir_name, validate_path_pattern};
+use crate::constant::{
+    DEFAULT_EXCLUDE_DIRS, DEFAULT_MAX_DIRECTORY_DEPTH, DEFAULT_MAX_FILES_PER_PROJECT,
+    DEFAULT_HIDDEN_DENY_DIRS, DEFAULT_QUARANTINE_RETENTION_DAYS,
+};
+use crate::cmd::PythonOptions;
+use crate::cargo::CargoOptions;
+use crate::go::{GoCache, GoOptions};
+use crate::node::NodeOptions;
+use crate::delete::DeleteMode;
+use crate::manifest::History;
+use crate::quarantine::Quarantine;
+use crate::size::SizeBudget;
+use crate::trash::home_trash_dir;
+use crate::utils::one_or_many;
+use crate::{dedupe_roots, CleanRoot, HiddenPolicy, WalkOptions};
+use std::time::Duration;
 
 /// Configuration for the clean command
-#[derive(Debug, Clone, Default, Serialize, Deserialize, Parser)]
+#[derive(Debug, Clone, Serialize, Deserialize, Parser)]
+#[serde(default)]
 #[clap(author, version, about = "A tool to clean up various project-related files and directories.", long_about = None)]
 pub struct Config {
-    /// Path to the project directory to clean
-    #[clap(short, long, value_parser, default_value = ".")]
-    pub path: PathBuf,
+    /// Directories to clean (repeatable; defaults to the current directory)
+    #[clap(short, long, value_parser, num_args = 1..)]
+    #[serde(deserialize_with = "one_or_many")]
+    pub path: Vec<PathBuf>,
+
+    /// Additional roots with their own settings (set in the config file as `[[roots]]`)
+    #[clap(skip)]
+    pub roots: Vec<RootConfig>,
 
     /// Exclude directories from cleaning
-    #[clap(short, long, value_parser, num_args = 1.., value_delimiter = ' ', default_values_t = ["node_modules".to_string(), "target".to_string(), "dist".to_string(), "build".to_string(), "vendor".to_string()])]
+    #[clap(short, long, value_parser, num_args = 1.., value_delimiter = ' ', default_values_t = DEFAULT_EXCLUDE_DIRS.map(String::from))]
     pub exclude_dir: Vec<String>,
 
-    /// Maximum depth to search for directories
+    /// Load settings from a TOML file; options given on the command line take precedence
+    #[clap(long = "config", value_parser, value_name = "FILE")]
+    #[serde(skip)]
+    pub config_file: Option<PathBuf>,
+
+    /// Never clean inside this directory (added to the built-in system directories)
+    #[clap(long = "protect", value_parser, value_name = "DIR")]
+    pub protected_roots: Vec<PathBuf>,
+
+    /// Only allow cleaning inside these directories (set in the config file)
+    #[clap(skip)]
+    pub allowed_roots: Vec<PathBuf>,
+
+    /// Clean the given path even if the safety policy blocks it
+    #[clap(long, action)]
+    #[serde(skip)]
+    pub allow_root: bool,
+
+    /// Skip paths matching a glob pattern relative to the root (e.g. `clients/legacy/*`, `**/third_party/**`);
+    /// applies to projects and to every artifact path, including those inside matching directories
+    #[clap(long, value_parser, value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+
+    /// Only clean projects inside subtrees matching a glob pattern relative to the root
+    #[clap(long, value_parser, value_name = "PATTERN")]
+    pub include: Vec<String>,
+
+    /// Also scan hidden (dot) directories, except those in --hidden-deny
+    #[clap(long, action)]
+    pub hidden: bool,
+
+    /// Hidden directory names that are never scanned, even with --hidden
+    #[clap(long, value_parser, value_name = "NAME", num_args = 1.., default_values_t = DEFAULT_HIDDEN_DENY_DIRS.map(String::from))]
+    pub hidden_deny: Vec<String>,
+
+    /// Follow directory symlinks while scanning (symlink loops are detected and skipped)
+    #[clap(long, action)]
+    pub follow_symlinks: bool,
+
+    /// Stay on the root's filesystem: do not scan or delete across mount points
+    #[clap(long, action)]
+    pub one_file_system: bool,
+
+    /// Maximum depth when measuring a whole project (ecosystems without declared artifact paths)
     #[clap(long, value_parser, default_value_t = DEFAULT_MAX_DIRECTORY_DEPTH)]
     pub max_directory_depth: usize,
 
-    /// Maximum number of files to process per project
+    /// Maximum number of files to measure per project (ecosystems without declared artifact paths)
     #[clap(long, value_parser, default_value_t = DEFAULT_MAX_FILES_PER_PROJECT)]
     pub max_files_per_project: usize,
 
+    /// Maximum depth when measuring artifact directories (unlimited by default)
+    #[clap(long, value_parser)]
+    pub max_artifact_depth: Option<usize>,
+
+    /// Maximum number of files to measure per artifact directory (unlimited by default)
+    #[clap(long, value_parser)]
+    pub max_artifact_files: Option<usize>,
+
+    /// Skip re-measuring projects cleaned by external commands (e.g. `cargo clean`)
+    #[clap(long, action)]
+    pub no_verify_size: bool,
+
+    /// Also remove Python virtual environments (detected by `pyvenv.cfg` or `conda-meta`)
+    #[clap(long, action)]
+    #[serde(skip)]
+    pub deep: bool,
+
+    /// Python settings (set in the config file as `[python]`)
+    #[clap(skip)]
+    pub python: PythonOptions,
+
+    /// After cleaning, run each detected package manager's cache prune command once (`pnpm store prune`, `yarn cache clean`, ...)
+    #[clap(long, action)]
+    #[serde(skip)]
+    pub prune_cache: bool,
+
+    /// Node.js settings (set in the config file as `[node]`)
+    #[clap(skip)]
+    pub node: NodeOptions,
+
+    /// Global Go caches to clear once per run when Go modules are found (cache, testcache, modcache, fuzzcache)
+    #[clap(long, value_enum, value_delimiter = ',', num_args = 1.., value_name = "CACHE")]
+    #[serde(skip)]
+    pub go_cache: Vec<GoCache>,
+
+    /// Also remove `bin/` and `dist/` build outputs of Go modules (Makefile, GoReleaser or gitignored)
+    #[clap(long, action)]
+    #[serde(skip)]
+    pub go_outputs: bool,
+
+    /// Go settings (set in the config file as `[go]`)
+    #[clap(skip)]
+    pub go: GoOptions,
+
+    /// Sweep Cargo targets instead of running `cargo clean`: remove artifacts unused for DAYS days
+    /// or built by the last-used rustc after that toolchain was uninstalled
+    #[clap(long, value_parser, value_name = "DAYS")]
+    #[serde(skip)]
+    pub sweep: Option<u64>,
+
+    /// Only remove the outputs of these Cargo profiles (e.g. `dev,test` removes `target/debug`) instead of running `cargo clean`
+    #[clap(long = "profile", value_parser, value_delimiter = ',', num_args = 1.., value_name = "PROFILE", conflicts_with = "sweep")]
+    #[serde(skip)]
+    pub cargo_profile: Vec<String>,
+
+    /// Only remove the outputs of these Cargo target triples; without `--profile` or `--doc` the whole `target/<TRIPLE>` goes
+    #[clap(long = "target", value_parser, value_delimiter = ',', num_args = 1.., value_name = "TRIPLE", conflicts_with = "sweep")]
+    #[serde(skip)]
+    pub cargo_target: Vec<String>,
+
+    /// Only remove Cargo documentation (`target/doc`), together with any `--profile` selection
+    #[clap(long = "doc", action, conflicts_with = "sweep")]
+    #[serde(skip)]
+    pub cargo_doc: bool,
+
+    /// Cargo settings (set in the config file as `[cargo]`)
+    #[clap(skip)]
+    pub cargo: CargoOptions,
+
+    /// Make read-only files and directories writable (chmod u+w) and retry when deletion is denied
+    #[clap(long, action)]
+    pub fix_permissions: bool,
+
+    /// Move artifacts to the desktop trash instead of deleting them
+    #[clap(long, action, conflicts_with = "quarantine")]
+    pub trash: bool,
+
+    /// Move artifacts to a quarantine directory instead of deleting them
+    #[clap(long, value_parser, value_name = "DIR")]
+    pub quarantine: Option<PathBuf>,
+
+    /// Days to keep quarantined artifacts before `rs_clean purge` removes them
+    #[clap(long, value_parser, default_value_t = DEFAULT_QUARANTINE_RETENTION_DAYS)]
+    pub retention_days: u64,
+
+    /// Directory for run manifests used by `undo` and `history` (defaults to ~/.rs_clean/history)
+    #[clap(long, value_parser, value_name = "DIR")]
+    pub history_dir: Option<PathBuf>,
+
+    /// Do not record this run in the history
+    #[clap(long, action)]
+    pub no_history: bool,
+
     /// Enable verbose output
     #[clap(short, long, action)]
     pub verbose: bool,
@@ -34,6 +192,66 @@ pub struct Config {
     pub dry_run: bool,
 }
 
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            path: vec![],
+            roots: vec![],
+            exclude_dir: DEFAULT_EXCLUDE_DIRS.map(String::from).to_vec(),
+            config_file: None,
+            protected_roots: vec![],
+            allowed_roots: vec![],
+            allow_root: false,
+            exclude: vec![],
+            include: vec![],
+            hidden: false,
+            hidden_deny: DEFAULT_HIDDEN_DENY_DIRS.map(String::from).to_vec(),
+            follow_symlinks: false,
+            one_file_system: false,
+            max_directory_depth: DEFAULT_MAX_DIRECTORY_DEPTH,
+            max_files_per_project: DEFAULT_MAX_FILES_PER_PROJECT,
+            max_artifact_depth: None,
+            max_artifact_files: None,
+            no_verify_size: false,
+            deep: false,
+            python: PythonOptions::default(),
+            prune_cache: false,
+            node: NodeOptions::default(),
+            go_cache: vec![],
+            go_outputs: false,
+            go: GoOptions::default(),
+            sweep: None,
+            cargo_profile: vec![],
+            cargo_target: vec![],
+            cargo_doc: false,
+            cargo: CargoOptions::default(),
+            fix_permissions: false,
+            trash: false,
+            quarantine: None,
+            retention_days: DEFAULT_QUARANTINE_RETENTION_DAYS,
+            history_dir: None,
+            no_history: false,
+            verbose: false,
+            dry_run: false,
+        }
+    }
+}
+
+/// A root from the config file; unset fields fall back to the top-level options
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RootConfig {
+    pub path: PathBuf,
+    /// Overrides `hidden` for this root
+    pub hidden: Option<bool>,
+    /// Overrides `hidden_deny` for this root
+    pub hidden_deny: Option<Vec<String>>,
+    /// Exclude patterns relative to this root, added to the top-level `exclude`
+    pub exclude: Vec<String>,
+    /// Include patterns relative to this root, added to the top-level `include`
+    pub include: Vec<String>,
+}
+
 /// Errors that can occur during configuration loading or validation
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -43,6 +261,8 @@ pub enum ConfigError {
     Parse(#[from] toml::de::Error),
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
+    #[error("Refusing to clean '{}': {rule} (pass --allow-root to override)", crate::os_path::escape(path))]
+    PathBlocked { path: PathBuf, rule: String },
     #[error("Failed to serialize config: {0}")]
     Serialize(#[from] toml::ser::Error),
 }
@@ -55,10 +275,88 @@ impl Config {
         Ok(config)
     }
 
+    /// Apply `--config FILE`: values from the file are used unless the option was given on the command line
+    ///
+    /// `explicit` lists the fields set on the command line.
+    pub fn merge_config_file<S: AsRef<str>>(self, explicit: &[S]) -> Result<Self, ConfigError> {
+        let Some(file) = self.config_file.clone() else {
+            return Ok(self);
+        };
+        let from_file = toml::Value::try_from(Self::load_from_file(&file)?)?;
+        let from_cli = toml::Value::try_from(&self)?;
+        let (toml::Value::Table(mut merged), toml::Value::Table(cli)) = (from_file, from_cli) else {
+            return Err(ConfigError::InvalidConfig("Config must be a table".to_string()));
+        };
+        for field in explicit {
+            if let Some(value) = cli.get(field.as_ref()) {
+                merged.insert(field.as_ref().to_string(), value.clone());
+            }
+        }
+
+        let mut config: Config = toml::Value::Table(merged).try_into()?;
+        // 仅限命令行的选项
+        config.config_file = self.config_file;
+        config.allow_root = self.allow_root;
+        config.deep = self.deep;
+        config.prune_cache = self.prune_cache;
+        config.go_cache = self.go_cache;
+        config.go_outputs = self.go_outputs;
+        config.sweep = self.sweep;
+        config.cargo_profile = self.cargo_profile;
+        config.cargo_target = self.cargo_target;
+        config.cargo_doc = self.cargo_doc;
+        Ok(config)
+    }
+
+    /// Safety policy deciding which roots may be cleaned
+    pub fn safety_policy(&self) -> SafetyPolicy {
+        SafetyPolicy {
+            protected_roots: self.protected_roots.clone(),
+            allowed_roots: self.allowed_roots.clone(),
+            allow_root: self.allow_root,
+        }
+    }
+
+    /// Roots to clean: every `path` plus every `[[roots]]` entry, or the current directory when both are empty
+    ///
+    /// Duplicate roots and roots nested inside another root are dropped.
+    pub fn clean_roots(&self) -> Vec<CleanRoot> {
+        let walk = self.walk_options();
+        let mut roots: Vec<CleanRoot> = self.path.iter().map(|path| CleanRoot::new(path, walk.clone())).collect();
+        for root in &self.roots {
+            let mut walk = walk.clone();
+            walk.exclude.extend(root.exclude.iter().cloned());
+            walk.include.extend(root.include.iter().cloned());
+            if let Some(hidden) = root.hidden {
+                walk.hidden.include = hidden;
+            }
+            if let Some(deny) = &root.hidden_deny {
+                walk.hidden.deny = deny.clone();
+            }
+            roots.push(CleanRoot::new(&root.path, walk));
+        }
+        if roots.is_empty() {
+            roots.push(CleanRoot::new(".", walk));
+        }
+        dedupe_roots(roots)
+    }
+
     /// Validate and sanitize configuration values
     pub fn validate(&self) -> Result<(), ConfigError> {
-        // Validate and sanitize path
-        validate_and_sanitize_path(&self.path.to_string_lossy())?;
+        // Check every root against the safety policy
+        let policy = self.safety_policy();
+        let mut checked = vec![];
+        for root in self.clean_roots() {
+            checked.push(policy.check(&root.path)?);
+
+            // Validate hidden directory names and exclude / include patterns
+            for dir_name in &root.walk.hidden.deny {
+                validate_exclude_dir_name(dir_name)?;
+            }
+            for pattern in root.walk.exclude.iter().chain(&root.walk.include) {
+                validate_path_pattern(pattern)?;
+            }
+        }
 
         // Validate exclude directory names
         for dir_name in &self.exclude_dir {
@@ -79,8 +377,131 @@ impl Config {
             ));
         }
 
+        // Validate artifact measurement limits
+        if self.max_artifact_depth == Some(0) || self.max_artifact_files == Some(0) {
+            return Err(ConfigError::InvalidConfig(
+                "max_artifact_depth and max_artifact_files cannot be 0".to_string(),
+            ));
+        }
+
+        // Validate soft-delete options
+        if self.trash && self.quarantine.is_some() {
+            return Err(ConfigError::InvalidConfig(
+                "trash and quarantine cannot be used together".to_string(),
+            ));
+        }
+        if let Some(quarantine) = &self.quarantine {
+            // 隔离区位于扫描目录内时，被隔离的产物会在下次运行时再次被识别为项目
+            let quarantine = std::path::absolute(quarantine)?;
+            let quarantine = quarantine.canonicalize().unwrap_or(quarantine);
+            if checked.iter().any(|root| quarantine.starts_with(root)) {
+                return Err(ConfigError::InvalidConfig(
+                    "Quarantine directory cannot be inside a directory being cleaned".to_string(),
+                ));
+            }
+        }
+
         Ok(())
     }
+
+    /// How removed artifacts are disposed of
+    pub fn delete_mode(&self) -> Result<DeleteMode, ConfigError> {
+        if self.trash {
+            let trash_dir = home_trash_dir().map_err(|e| ConfigError::InvalidConfig(e.to_string()))?;
+            return Ok(DeleteMode::Trash(trash_dir));
+        }
+        Ok(match &self.quarantine {
+            Some(root) => DeleteMode::Quarantine(Quarantine::new(
+                root.clone(),
+                Duration::from_secs(self.retention_days.saturating_mul(24 * 60 * 60)),
+            )),
+            None => DeleteMode::Permanent,
+        })
+    }
+
+    /// Directory traversal options
+    pub fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            exclude_dirs: self.exclude_dir.clone(),
+            exclude: self.exclude.clone(),
+            include: self.include.clone(),
+            hidden: HiddenPolicy {
+                include: self.hidden,
+                deny: self.hidden_deny.clone(),
+            },
+            follow_symlinks: self.follow_symlinks,
+            one_file_system: self.one_file_system,
+        }
+    }
+
+    /// Python cleaning options; `--deep` turns on virtual environment removal
+    pub fn python_options(&self) -> PythonOptions {
+        PythonOptions {
+            remove_venvs: self.deep || self.python.remove_venvs,
+        }
+    }
+
+    /// Node.js cleaning options; `--prune-cache` turns on package manager cache pruning
+    pub fn node_options(&self) -> NodeOptions {
+        NodeOptions {
+            prune: self.prune_cache || self.node.prune,
+        }
+    }
+
+    /// Go cleaning options; `--go-cache` and `--go-outputs` add to the `[go]` section
+    pub fn go_options(&self) -> GoOptions {
+        let mut caches = self.go.caches.clone();
+        for cache in &self.go_cache {
+            if !caches.contains(cache) {
+                caches.push(*cache);
+            }
+        }
+        GoOptions {
+            caches,
+            remove_outputs: self.go_outputs || self.go.remove_outputs,
+        }
+    }
+
+    /// Cargo cleaning options; `--sweep` overrides `sweep_days` from the `[cargo]` section,
+    /// and a selection given with `--profile`, `--target` or `--doc` replaces the configured one
+    pub fn cargo_options(&self) -> CargoOptions {
+        let cli_selection = !self.cargo_profile.is_empty() || !self.cargo_target.is_empty() || self.cargo_doc;
+        let (profiles, targets, doc) = if cli_selection {
+            (self.cargo_profile.clone(), self.cargo_target.clone(), self.cargo_doc)
+        } else {
+            (self.cargo.profiles.clone(), self.cargo.targets.clone(), self.cargo.doc)
+        };
+        CargoOptions {
+            sweep_days: self.sweep.or(self.cargo.sweep_days),
+            profiles,
+            targets,
+            doc,
+        }
+    }
+
+    /// Where run manifests are written, or `None` when history is disabled
+    pub fn history(&self) -> Option<History> {
+        if self.no_history {
+            return None;
+        }
+        self.history_dir.clone().or_else(History::default_dir).map(History::new)
+    }
+
+    /// Budget used when a whole project directory has to be measured
+    pub fn project_budget(&self) -> SizeBudget {
+        SizeBudget {
+            max_depth: self.max_directory_depth,
+            max_files: self.max_files_per_project,
+        }
+    }
+
+    /// Budget used when measuring declared artifact directories
+    pub fn artifact_budget(&self) -> SizeBudget {
+        SizeBudget {
+            max_depth: self.max_artifact_depth.unwrap_or(SizeBudget::UNLIMITED.max_depth),
+            max_files: self.max_artifact_files.unwrap_or(SizeBudget::UNLIMITED.max_files),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -94,14 +515,132 @@ mod tests {
         let mut file = NamedTempFile::new().unwrap();
         writeln!(file, "path = \"./test_project\"\nexclude_dir = [\"target\", \"node_modules\"]").unwrap();
         let config = Config::load_from_file(file.path()).unwrap();
-        assert_eq!(config.path, PathBuf::from("./test_project"));
+        assert_eq!(config.path, vec![PathBuf::from("./test_project")]);
         assert_eq!(config.exclude_dir, vec!["target", "node_modules"]);
     }
 
+    #[test]
+    fn test_merge_config_file_prefers_command_line() {
+        let root = tempfile::TempDir::new().unwrap();
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "exclude_dir = [\"vendor\"]\nmax_directory_depth = 3\nallowed_roots = [{:?}]\nallow_root = true",
+            root.path()
+        )
+        .unwrap();
+
+        let cli = Config {
+            config_file: Some(file.path().to_path_buf()),
+            path: vec![root.path().to_path_buf()],
+            max_directory_depth: 9,
+            ..Default::default()
+        };
+        let config = cli.merge_config_file(&["path", "max_directory_depth"]).unwrap();
+        assert_eq!(config.path, vec![root.path().to_path_buf()]);
+        assert_eq!(config.max_directory_depth, 9);
+        assert_eq!(config.exclude_dir, vec!["vendor"]);
+        assert_eq!(config.allowed_roots, vec![root.path().to_path_buf()]);
+        // `allow_root` 只能在命令行上显式指定
+        assert!(!config.allow_root);
+        assert!(config.validate().is_ok());
+
+        let outside = Config { path: vec![PathBuf::from(".")], ..config };
+        assert!(matches!(outside.validate(), Err(ConfigError::PathBlocked { .. })));
+    }
+
+    #[test]
+    fn test_clean_roots_from_cli_and_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let work = dir.path().join("work");
+        std::fs::create_dir_all(work.join("nested")).unwrap();
+        std::fs::create_dir_all(dir.path().join("other")).unwrap();
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "path = {:?}\n[[roots]]\npath = {:?}\nhidden = true\nexclude = [\"vendor\"]\n[[roots]]\npath = {:?}",
+            work,
+            dir.path().join("other"),
+            work.join("nested")
+        )
+        .unwrap();
+        let config = Config::load_from_file(file.path()).unwrap();
+        let roots = config.clean_roots();
+
+        // 嵌套在 `work` 中的根目录被合并
+        let paths: Vec<_> = roots.iter().map(|root| root.path.clone()).collect();
+        assert_eq!(paths, vec![work.clone(), dir.path().join("other")]);
+        assert!(!roots[0].walk.hidden.include);
+        assert!(roots[1].walk.hidden.include);
+        assert_eq!(roots[1].walk.exclude, vec!["vendor"]);
+        assert!(config.validate().is_ok());
+
+        assert_eq!(Config::default().clean_roots()[0].path, PathBuf::from("."));
+    }
+
+    #[test]
+    fn test_python_options() {
+        assert!(!Config::default().python_options().remove_venvs);
+        assert!(Config { deep: true, ..Default::default() }.python_options().remove_venvs);
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "[python]\nremove_venvs = true").unwrap();
+        let config = Config::load_from_file(file.path()).unwrap();
+        assert!(config.python_options().remove_venvs);
+    }
+
+    #[test]
+    fn test_go_options() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "[go]\ncaches = [\"cache\", \"testcache\"]").unwrap();
+        let config = Config {
+            config_file: Some(file.path().to_path_buf()),
+            go_cache: vec![GoCache::Modcache, GoCache::Cache],
+            ..Default::default()
+        }
+        .merge_config_file(&["go_cache"])
+        .unwrap();
+        let options = config.go_options();
+        assert_eq!(options.caches, vec![GoCache::Cache, GoCache::Testcache, GoCache::Modcache]);
+        assert!(!options.remove_outputs);
+    }
+
+    #[test]
+    fn test_cargo_options() {
+        assert_eq!(Config::default().cargo_options().sweep_days, None);
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "[cargo]\nsweep_days = 30").unwrap();
+        let config = Config::load_from_file(file.path()).unwrap();
+        assert_eq!(config.cargo_options().sweep_days, Some(30));
+        assert_eq!(Config { sweep: Some(7), ..config }.cargo_options().sweep_days, Some(7));
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "[cargo]\nprofiles = [\"dev\"]\ndoc = true").unwrap();
+        let config = Config::load_from_file(file.path()).unwrap();
+        let options = config.cargo_options();
+        assert_eq!((options.profiles, options.targets, options.doc), (vec!["dev".to_string()], vec![], true));
+        // 命令行上的选择整体替换配置文件中的选择
+        let options = Config { cargo_target: vec!["wasm32-unknown-unknown".to_string()], ..config }.cargo_options();
+        assert_eq!((options.profiles, options.targets, options.doc), (vec![], vec!["wasm32-unknown-unknown".to_string()], false));
+        assert!(Config::try_parse_from(["rs_clean", "--sweep", "7", "--doc"]).is_err());
+    }
+
+    #[test]
+    fn test_node_options() {
+        assert!(!Config::default().node_options().prune);
+        assert!(Config { prune_cache: true, ..Default::default() }.node_options().prune);
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "[node]\nprune = true").unwrap();
+        let config = Config::load_from_file(file.path()).unwrap();
+        assert!(config.node_options().prune);
+    }
+
     #[test]
     fn test_validate_max_directory_depth() {
-        let mut config = Config::default();
-        config.max_directory_depth = 0;
+        let mut config = Config { max_directory_depth: 0, ..Default::default() };
         assert!(config.validate().is_err());
         config.max_directory_depth = 1;
         assert!(config.validate().is_ok());
@@ -109,10 +648,58 @@ mod tests {
 
     #[test]
     fn test_validate_max_files_per_project() {
-        let mut config = Config::default();
-        config.max_files_per_project = 0;
+        let mut config = Config { max_files_per_project: 0, ..Default::default() };
         assert!(config.validate().is_err());
         config.max_files_per_project = 1;
         assert!(config.validate().is_ok());
     }
+
+    #[test]
+    fn test_validate_path_patterns() {
+        let config = Config { exclude: vec!["**/third_party/**".to_string()], ..Default::default() };
+        assert!(config.validate().is_ok());
+        let config = Config { include: vec!["../elsewhere".to_string()], ..Default::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_artifact_budget() {
+        let config = Config::default();
+        assert_eq!(config.artifact_budget(), SizeBudget::UNLIMITED);
+
+        let config = Config { max_artifact_depth: Some(3), ..Default::default() };
+        assert_eq!(config.artifact_budget().max_depth, 3);
+
+        let config = Config { max_artifact_files: Some(0), ..Default::default() };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_quarantine_outside_root() {
+        let config = Config { quarantine: Some(PathBuf::from("./quarantine")), ..Default::default() };
+        assert!(config.validate().is_err());
+
+        let outside = tempfile::TempDir::new().unwrap();
+        let config = Config { quarantine: Some(outside.path().to_path_buf()), ..Default::default() };
+        assert!(config.validate().is_ok());
+        assert!(matches!(config.delete_mode().unwrap(), DeleteMode::Quarantine(_)));
+        assert!(matches!(Config::default().delete_mode().unwrap(), DeleteMode::Permanent));
+    }
+
+    #[test]
+    fn test_huge_retention_does_not_overflow() {
+        let outside = tempfile::TempDir::new().unwrap();
+        let config = Config {
+            quarantine: Some(outside.path().to_path_buf()),
+            retention_days: u64::MAX,
+            ..Default::default()
+        };
+        let DeleteMode::Quarantine(quarantine) = config.delete_mode().unwrap() else {
+            panic!("expected quarantine mode");
+        };
+        let project = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(project.path().join("target")).unwrap();
+        quarantine.store(&project.path().join("target"), project.path()).unwrap();
+        assert!(quarantine.purge(false, 1).unwrap().is_empty());
+    }
 }
\ No newline at end of file
@@ -4,7 +4,83 @@ use crate::cmd::CommandType; // 引入 CommandType
 
 pub const DEFAULT_MAX_DIRECTORY_DEPTH: usize = 5;
 pub const DEFAULT_MAX_FILES_PER_PROJECT: usize = 10000;
+/// Marker file that skips a project (and its subdirectories) when placed in its directory
+pub const KEEP_MARKER: &str = ".rs_clean_keep";
+/// File of gitignore-style exclusion rules, with patterns relative to its directory
+pub const IGNORE_FILE: &str = ".rs_cleanignore";
+pub const DEFAULT_QUARANTINE_RETENTION_DAYS: u64 = 7;
+/// Built-in protected directories: neither they nor any path below them can be a cleaning root
+pub const DEFAULT_PROTECTED_ROOTS: &[&str] = &[
+    // Unix 系统目录
+    "/etc", "/usr", "/bin", "/sbin", "/lib", "/lib32", "/lib64", "/boot", "/dev", "/proc", "/sys",
+    // macOS 系统目录
+    "/System", "/Library", "/Applications", "/private/etc",
+    // Windows 系统目录
+    "C:\\Windows", "C:\\Program Files", "C:\\Program Files (x86)", "C:\\ProgramData",
+];
+/// Directories that can't be cleaned themselves, though projects below them can
+pub const DEFAULT_PROTECTED_DIRS: &[&str] = &[
+    // macOS 上 /var 和 /tmp 是指向 /private 下的符号链接
+    "/var/tmp", "/private/var", "/private/tmp",
+];
+/// Hidden directories still skipped with `--hidden`
+pub const DEFAULT_HIDDEN_DENY_DIRS: [&str; 4] = [".git", ".hg", ".svn", ".cache"];
+pub const DEFAULT_EXCLUDE_DIRS: [&str; 5] = ["node_modules", "target", "dist", "build", "vendor"];
+/// Cache directories that can appear in Python package directories at any depth
+pub const PYTHON_NESTED_CACHE_DIRS: [&str; 2] = ["__pycache__", ".ipynb_checkpoints"];
+/// Extensions of Python bytecode files scattered next to the sources
+pub const PYTHON_COMPILED_EXTENSIONS: [&str; 2] = ["pyc", "pyo"];
 
+/// Node.js tools: when `packages` appear in the dependencies (`@scope/*` matches a whole scope) or the scripts call `commands`,
+/// the `outputs` are considered generated by the tool
+pub struct NodeTool {
+    pub packages: &'static [&'static str],
+    pub commands: &'static [&'static str],
+    pub outputs: &'static [&'static str],
+}
+
+pub const NODE_TOOLS: &[NodeTool] = &[
+    NodeTool { packages: &["next"], commands: &["next"], outputs: &[".next", "out"] },
+    NodeTool { packages: &["nuxt", "nuxt3"], commands: &["nuxt", "nuxi"], outputs: &[".nuxt", ".output"] },
+    NodeTool { packages: &["@sveltejs/kit"], commands: &["svelte-kit"], outputs: &[".svelte-kit", "build"] },
+    NodeTool { packages: &["@angular/cli"], commands: &["ng"], outputs: &[".angular", "dist"] },
+    NodeTool { packages: &["parcel"], commands: &["parcel"], outputs: &[".parcel-cache", "dist"] },
+    NodeTool { packages: &["vite"], commands: &["vite"], outputs: &[".vite", "dist"] },
+    NodeTool { packages: &["astro"], commands: &["astro"], outputs: &[".astro", "dist"] },
+    NodeTool { packages: &["turbo"], commands: &["turbo"], outputs: &[".turbo"] },
+    NodeTool { packages: &["storybook", "@storybook/*"], commands: &["storybook", "build-storybook"], outputs: &["storybook-static"] },
+    NodeTool { packages: &["@docusaurus/core"], commands: &["docusaurus"], outputs: &[".docusaurus", "build"] },
+    NodeTool { packages: &["react-scripts"], commands: &["react-scripts"], outputs: &["build"] },
+    NodeTool { packages: &["gatsby"], commands: &["gatsby"], outputs: &[".cache", "public"] },
+    NodeTool { packages: &["@remix-run/dev"], commands: &["remix"], outputs: &[".cache", "build"] },
+    NodeTool { packages: &["webpack", "webpack-cli"], commands: &["webpack"], outputs: &["dist"] },
+    NodeTool { packages: &["rollup"], commands: &["rollup"], outputs: &["dist"] },
+    NodeTool { packages: &["jest", "vitest", "nyc", "c8"], commands: &["jest", "vitest", "nyc", "c8"], outputs: &["coverage"] },
+];
+
+/// Generic directory names removed only when gitignored, if no tool generating them was detected
+pub const NODE_GENERIC_OUTPUTS: &[&str] = &["build", "dist", "out", "coverage", ".cache"];
+
+/// Marker files in a workspace root and the cache their tool keeps under the root
+pub const NODE_WORKSPACE_CACHES: &[(&str, &[&str])] = &[
+    ("nx.json", &[".nx/cache", ".nx/workspace-data"]),
+    ("turbo.json", &[".turbo/cache", "node_modules/.cache/turbo"]),
+];
+
+/// Local files created by Yarn Berry (Plug'n'Play) installs; `.yarn/cache` is handled separately (zero-install projects check it in)
+pub const YARN_PNP_ARTIFACTS: &[&str] = &[
+    ".yarn/unplugged",
+    ".yarn/install-state.gz",
+    ".yarn/build-state.yml",
+    ".pnp.cjs",
+    ".pnp.loader.mjs",
+    ".pnp.js",
+];
+
+/// GoReleaser config files; when one exists, `dist/` is treated as its output
+pub const GORELEASER_CONFIGS: &[&str] = &[".goreleaser.yml", ".goreleaser.yaml", "goreleaser.yml", "goreleaser.yaml"];
+/// Makefiles that may refer to a `bin/` output directory
+pub const MAKEFILE_NAMES: &[&str] = &["Makefile", "makefile", "GNUmakefile"];
 
 static CMD_MAP: OnceLock<HashMap<CommandType, Vec<&'static str>>> = OnceLock::new();
 
@@ -16,7 +92,10 @@ pub fn get_cmd_map() -> &'static HashMap<CommandType, Vec<&'static str>> {
         m.insert(CommandType::Gradle, vec!["build.gradle", "build.gradle.kts"]);
         m.insert(CommandType::NodeJs, vec!["package.json"]); // 统一使用 nodejs 标识符
         m.insert(CommandType::Flutter, vec!["pubspec.yaml"]);
-        m.insert(CommandType::Python, vec!["requirements.txt", "pyproject.toml"]); // Python projects
+        m.insert(
+            CommandType::Python,
+            vec!["requirements.txt", "pyproject.toml", "setup.py", "setup.cfg", "Pipfile", "environment.yml"],
+        ); // Python projects
         #[cfg(not(target_os = "windows"))]
         {
             m.insert(CommandType::Maven, vec!["pom.xml"]);
@@ -29,6 +108,46 @@ pub fn get_cmd_map() -> &'static HashMap<CommandType, Vec<&'static str>> {
     })
 }
 
+static ARTIFACT_MAP: OnceLock<HashMap<CommandType, Vec<&'static str>>> = OnceLock::new();
+
+/// Build artifact paths per ecosystem (relative to the project root, `*` wildcards allowed)
+pub fn get_artifact_map() -> &'static HashMap<CommandType, Vec<&'static str>> {
+    ARTIFACT_MAP.get_or_init(|| {
+        let mut m = HashMap::new();
+        m.insert(CommandType::Cargo, vec!["target"]);
+        m.insert(CommandType::Go, vec![]);
+        m.insert(CommandType::Gradle, vec!["build"]);
+        m.insert(
+            CommandType::NodeJs,
+            // 框架输出目录由 `node` 模块根据 package.json 和 .gitignore 决定
+            vec!["node_modules"],
+        );
+        m.insert(CommandType::Flutter, vec!["build", ".dart_tool"]);
+        m.insert(
+            CommandType::Python,
+            vec![
+                "__pycache__",
+                "build",
+                "dist",
+                ".eggs",
+                "*.egg-info",
+                ".pytest_cache",
+                "htmlcov",
+                ".mypy_cache",
+                ".ruff_cache",
+                ".pytype",
+                ".hypothesis",
+                ".ipynb_checkpoints",
+                ".tox",
+                ".nox",
+            ],
+        );
+        m.insert(CommandType::Maven, vec!["target"]);
+        m.insert(CommandType::MavenCmd, vec!["target"]);
+        m
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,6 +177,12 @@ mod tests {
         // 测试 Node.js 命令
         assert_eq!(map.get(&CommandType::NodeJs), Some(&vec!["package.json"]));
 
+        // 测试 Python 命令
+        assert_eq!(
+            map.get(&CommandType::Python),
+            Some(&vec!["requirements.txt", "pyproject.toml", "setup.py", "setup.cfg", "Pipfile", "environment.yml"])
+        );
+
         // 测试 Maven 命令（平台相关）
         #[cfg(not(target_os = "windows"))]
         {
@@ -71,4 +196,13 @@ mod tests {
         // 验证总数
         assert_eq!(map.len(), 7);
     }
+
+    #[test]
+    fn test_get_artifact_map_covers_all_types() {
+        let artifacts = get_artifact_map();
+        for cmd_type in get_cmd_map().keys() {
+            assert!(artifacts.contains_key(cmd_type), "missing artifacts for {}", cmd_type.as_str());
+        }
+        assert_eq!(artifacts.get(&CommandType::Cargo), Some(&vec!["target"]));
+    }
 }
@@ -0,0 +1,624 @@
+use crate::quarantine::Quarantine;
+use crate::size::WorkQueues;
+use crate::trash::move_to_trash;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+/// Prefix of tombstone directory names; it starts with `.`, so scans skip tombstones as hidden directories
+pub const TOMBSTONE_PREFIX: &str = ".rs_clean-tombstone-";
+/// Written into a tombstone that holds a mount point of another filesystem; later runs no longer try to remove it
+const MOUNTED_TOMBSTONE_MARKER: &str = ".rs_clean-mounted";
+const FOREIGN_MOUNT_ERROR: &str = "mount point of another filesystem; unmount it and delete the directory by hand";
+
+static TOMBSTONE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Whether a directory name is a tombstone left by rs_clean
+pub fn is_tombstone(name: &OsStr) -> bool {
+    name.as_encoded_bytes().starts_with(TOMBSTONE_PREFIX.as_bytes())
+}
+
+/// Tombstone path in the project directory
+///
+/// Artifacts may live in directories the scan skips (such as `target/debug/deps`) or outside the scan roots (a relocated Cargo target directory),
+/// so tombstones go into the project directory, which is always walked, and an interrupted purge is found by the next run. Renaming fails across filesystems and the artifact is then removed in place.
+fn tombstone_path(path: &Path, project: &Path) -> Option<PathBuf> {
+    let mut name = OsString::from(TOMBSTONE_PREFIX);
+    name.push(path.file_name()?);
+    name.push(format!(
+        "-{}-{}",
+        std::process::id(),
+        TOMBSTONE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    Some(project.join(name))
+}
+
+/// Whether a removal failed on a mount point of another filesystem; such failures are not retried on the next run
+pub fn is_foreign_mount(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::Other && e.to_string() == FOREIGN_MOUNT_ERROR
+}
+
+/// Device number of a file; `None` on non-Unix platforms where it can't be told
+#[cfg(unix)]
+pub fn device_of(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+pub fn device_of(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Options for removing a directory tree
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PurgeOptions {
+    pub threads: usize,
+    /// Don't descend into subdirectories on another device (mount points)
+    pub one_file_system: bool,
+    /// On permission errors, add write permission for the current user and retry (`chmod u+w`)
+    pub fix_permissions: bool,
+}
+
+/// Paths that could not be removed from a tree, with the reason
+pub type PurgeFailures = Vec<(PathBuf, io::Error)>;
+
+/// Remove a directory tree with several threads: each directory is removed once its files are deleted and its last subdirectory is gone
+pub fn purge_tree(path: &Path, threads: usize) -> io::Result<()> {
+    purge_tree_with(path, PurgeOptions { threads, ..Default::default() }).map_err(summarize_failures)
+}
+
+/// A directory waiting to be removed
+struct PurgeItem {
+    path: PathBuf,
+    parent: Option<Arc<PurgeDir>>,
+}
+
+/// A directory whose entries have been read; it is removed after all its subdirectories
+struct PurgeDir {
+    path: PathBuf,
+    /// Subdirectories not yet finished, plus one for reading this directory
+    remaining: AtomicUsize,
+    /// Some content could not be removed, so the directory itself is not attempted
+    failed: AtomicBool,
+    parent: Option<Arc<PurgeDir>>,
+}
+
+/// Like [`purge_tree`], but removes everything it can and returns every path that could not be removed
+pub fn purge_tree_with(path: &Path, options: PurgeOptions) -> Result<(), PurgeFailures> {
+    let fix = options.fix_permissions;
+    let metadata = std::fs::symlink_metadata(path).map_err(|e| vec![(path.to_path_buf(), e)])?;
+    if !metadata.is_dir() {
+        // 符号链接只删除链接本身，不会进入其指向的目录
+        return remove_with_retry(path, false, fix).map_err(|e| vec![(path.to_path_buf(), e)]);
+    }
+    let root_device = device_of(&metadata).filter(|_| options.one_file_system);
+
+    let threads = options.threads.max(1);
+    let queues = WorkQueues::new(threads, [PurgeItem { path: path.to_path_buf(), parent: None }]);
+    let foreign_mounts = AtomicBool::new(false);
+    let failures: Mutex<PurgeFailures> = Mutex::new(vec![]);
+    let fail = |path: PathBuf, e: io::Error| failures.lock().unwrap().push((path, e));
+
+    std::thread::scope(|scope| {
+        for worker in 0..threads {
+            let (queues, foreign_mounts, fail) = (&queues, &foreign_mounts, &fail);
+            scope.spawn(move || {
+                while let Some(PurgeItem { path: dir, parent }) = queues.next(worker) {
+                    let mut subdirs = vec![];
+                    let mut failed = false;
+                    let entries = match std::fs::read_dir(&dir) {
+                        Err(e) if fix && e.kind() == io::ErrorKind::PermissionDenied => {
+                            let _ = make_writable(&dir);
+                            std::fs::read_dir(&dir)
+                        }
+                        result => result,
+                    };
+                    match entries {
+                        Ok(entries) => {
+                            for entry in entries.flatten() {
+                                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                                let foreign = is_dir
+                                    && root_device.is_some()
+                                    && entry.metadata().ok().as_ref().and_then(device_of) != root_device;
+                                if foreign {
+                                    foreign_mounts.store(true, Ordering::Relaxed);
+                                    fail(entry.path(), io::Error::other(FOREIGN_MOUNT_ERROR));
+                                    failed = true;
+                                } else if is_dir {
+                                    subdirs.push(entry.path());
+                                } else if let Err(e) = remove_with_retry(&entry.path(), false, fix) {
+                                    fail(entry.path(), e);
+                                    failed = true;
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            fail(dir.clone(), e);
+                            failed = true;
+                        }
+                    }
+
+                    let node = Arc::new(PurgeDir {
+                        path: dir,
+                        remaining: AtomicUsize::new(subdirs.len() + 1),
+                        failed: AtomicBool::new(failed),
+                        parent,
+                    });
+                    let items = subdirs.into_iter().map(|path| PurgeItem { path, parent: Some(Arc::clone(&node)) });
+                    queues.push(worker, items.collect());
+                    finish_dir(node, fix, fail);
+                    queues.done();
+                }
+            });
+        }
+    });
+
+    // 挂载点无法删除，记录下来以免每次运行都重新报告同一个墓碑
+    if foreign_mounts.into_inner() && path.file_name().is_some_and(is_tombstone) {
+        let _ = std::fs::write(path.join(MOUNTED_TOMBSTONE_MARKER), "");
+    }
+
+    let failures = failures.into_inner().unwrap();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures)
+    }
+}
+
+/// One piece of work on a directory is done; when all of it is, remove the directory and continue with its parent
+fn finish_dir(mut dir: Arc<PurgeDir>, fix: bool, fail: &impl Fn(PathBuf, io::Error)) {
+    while dir.remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+        // 内容没删干净的目录不再重复报告
+        let removed = !dir.failed.load(Ordering::Acquire)
+            && remove_with_retry(&dir.path, true, fix).map_err(|e| fail(dir.path.clone(), e)).is_ok();
+        let Some(parent) = dir.parent.clone() else {
+            return;
+        };
+        if !removed {
+            parent.failed.store(true, Ordering::Release);
+        }
+        dir = parent;
+    }
+}
+
+/// Add write permission for the current user, plus read and execute on directories; symlinks are left alone
+pub fn make_writable(path: &Path) -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.file_type().is_symlink() {
+        return Ok(());
+    }
+    let mut permissions = metadata.permissions();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let extra = if metadata.is_dir() { 0o700 } else { 0o200 };
+        if permissions.mode() & extra == extra {
+            return Ok(());
+        }
+        permissions.set_mode(permissions.mode() | extra);
+    }
+    #[cfg(not(unix))]
+    {
+        if !permissions.readonly() {
+            return Ok(());
+        }
+        permissions.set_readonly(false);
+    }
+    std::fs::set_permissions(path, permissions)
+}
+
+/// Remove a path; on a permission error with fixing allowed, make the parent and the path writable and retry once
+fn remove_with_retry(path: &Path, is_dir: bool, fix_permissions: bool) -> io::Result<()> {
+    let remove = |path: &Path| if is_dir { std::fs::remove_dir(path) } else { std::fs::remove_file(path) };
+    match remove(path) {
+        Err(e) if fix_permissions && e.kind() == io::ErrorKind::PermissionDenied => {
+            if let Some(parent) = path.parent() {
+                make_writable(parent)?;
+            }
+            make_writable(path)?;
+            remove(path)
+        }
+        result => result,
+    }
+}
+
+/// Combine several removal failures into one error, listing at most the first three
+pub fn summarize_failures(failures: PurgeFailures) -> io::Error {
+    let kind = failures.first().map_or(io::ErrorKind::Other, |(_, e)| e.kind());
+    let mut listed: Vec<String> = failures
+        .iter()
+        .take(3)
+        .map(|(path, e)| format!("{}: {}", path.display(), e))
+        .collect();
+    if failures.len() > 3 {
+        listed.push(format!("and {} more", failures.len() - 3));
+    }
+    io::Error::new(kind, format!("cannot remove {}", listed.join("; ")))
+}
+
+/// A background purge that returns the paths it could not remove
+type PurgeHandle = JoinHandle<Result<(), PurgeFailures>>;
+
+/// What happens to removed build artifacts
+#[derive(Debug, Clone, Default)]
+pub enum DeleteMode {
+    /// Delete permanently
+    #[default]
+    Permanent,
+    /// Move to the freedesktop.org trash (the trash directory)
+    Trash(PathBuf),
+    /// Move to quarantine, purged by `purge` after the retention period
+    Quarantine(Quarantine),
+}
+
+/// Where a removed path went, for undo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Disposal {
+    /// Deleted permanently
+    Deleted,
+    /// Location under the trash's `files/`
+    Trashed {
+        #[serde(with = "crate::os_path")]
+        location: PathBuf,
+    },
+    /// Entry directory in quarantine
+    Quarantined {
+        #[serde(with = "crate::os_path")]
+        entry: PathBuf,
+    },
+}
+
+/// Deletion engine: atomically rename a directory to a tombstone, then purge tombstones in parallel in the background
+pub struct Deleter {
+    threads: usize,
+    mode: DeleteMode,
+    one_file_system: bool,
+    fix_permissions: bool,
+    disposals: Mutex<HashMap<PathBuf, Disposal>>,
+    purge_slots: Arc<Semaphore>,
+    background: Mutex<Vec<PurgeHandle>>,
+}
+
+impl Deleter {
+    pub fn new(threads: usize) -> Self {
+        Self::with_mode(threads, DeleteMode::Permanent)
+    }
+
+    pub fn with_mode(threads: usize, mode: DeleteMode) -> Self {
+        Self {
+            threads: threads.max(1),
+            mode,
+            one_file_system: false,
+            fix_permissions: false,
+            disposals: Mutex::new(HashMap::new()),
+            // 同一时间只清除一个墓碑，每个墓碑内部再并行删除
+            purge_slots: Arc::new(Semaphore::new(1)),
+            background: Mutex::new(vec![]),
+        }
+    }
+
+    /// Don't remove paths on a different device than their parent, and don't descend into mount points inside them
+    pub fn one_file_system(mut self, enabled: bool) -> Self {
+        self.one_file_system = enabled;
+        self
+    }
+
+    /// Add write permission to read-only files and directories and retry
+    pub fn fix_permissions(mut self, enabled: bool) -> Self {
+        self.fix_permissions = enabled;
+        self
+    }
+
+    fn purge_options(&self) -> PurgeOptions {
+        PurgeOptions {
+            threads: self.threads,
+            one_file_system: self.one_file_system,
+            fix_permissions: self.fix_permissions,
+        }
+    }
+
+    pub fn mode(&self) -> &DeleteMode {
+        &self.mode
+    }
+
+    /// Where a removed path went
+    pub fn disposal_of(&self, path: &Path) -> Option<Disposal> {
+        self.disposals.lock().unwrap().get(path).cloned()
+    }
+
+    fn record(&self, path: &Path, disposal: Disposal) {
+        self.disposals.lock().unwrap().insert(path.to_path_buf(), disposal);
+    }
+
+    /// Remove `path`, which belongs to `project`; returns `Ok(false)` when the path doesn't exist
+    ///
+    /// Once the rename succeeds the project looks clean right away and the actual deletion runs in the background.
+    /// When renaming isn't possible (for example on a mount point), the path is removed in place.
+    /// Trash and quarantine modes only move the path and return an error when the move fails.
+    /// For a symlink only the link itself is removed (or moved); its target is never entered.
+    pub async fn remove(&self, path: &Path, project: &Path) -> io::Result<bool> {
+        let Ok(metadata) = tokio::fs::symlink_metadata(path).await else {
+            return Ok(false);
+        };
+        if self.one_file_system {
+            let parent = path.parent().unwrap_or(path);
+            let parent_device = tokio::fs::metadata(parent).await.ok().as_ref().and_then(device_of);
+            if device_of(&metadata) != parent_device {
+                return Err(io::Error::other(format!(
+                    "{} is a mount point on a different filesystem",
+                    path.display()
+                )));
+            }
+        }
+
+        let target = path.to_path_buf();
+        match &self.mode {
+            DeleteMode::Permanent => {}
+            DeleteMode::Trash(trash_dir) => {
+                let trash_dir = trash_dir.clone();
+                let location = tokio::task::spawn_blocking(move || move_to_trash(&target, &trash_dir))
+                    .await
+                    .map_err(io::Error::other)??;
+                self.record(path, Disposal::Trashed { location });
+                return Ok(true);
+            }
+            DeleteMode::Quarantine(quarantine) => {
+                let quarantine = quarantine.clone();
+                let project = project.to_path_buf();
+                let entry = tokio::task::spawn_blocking(move || quarantine.store(&target, &project))
+                    .await
+                    .map_err(io::Error::other)??;
+                self.record(path, Disposal::Quarantined { entry });
+                return Ok(true);
+            }
+        }
+
+        match tombstone_path(path, project) {
+            // 单个文件（例如散落的 `*.pyc`）直接删除，墓碑只用于目录
+            _ if !metadata.is_dir() => {
+                let fix_permissions = self.fix_permissions;
+                tokio::task::spawn_blocking(move || remove_with_retry(&target, false, fix_permissions))
+                    .await
+                    .map_err(io::Error::other)??;
+            }
+            Some(tombstone) if tokio::fs::rename(path, &tombstone).await.is_ok() => {
+                self.schedule_purge(tombstone);
+            }
+            _ => {
+                let options = self.purge_options();
+                tokio::task::spawn_blocking(move || purge_tree_with(&target, options))
+                    .await
+                    .map_err(io::Error::other)?
+                    .map_err(summarize_failures)?;
+            }
+        }
+        self.record(path, Disposal::Deleted);
+        Ok(true)
+    }
+
+    /// Purge a tombstone directory in the background
+    pub fn schedule_purge(&self, tombstone: PathBuf) {
+        let options = self.purge_options();
+        let purge_slots = Arc::clone(&self.purge_slots);
+        let handle = tokio::spawn(async move {
+            let _permit = purge_slots.acquire_owned().await;
+            let target = tombstone.clone();
+            tokio::task::spawn_blocking(move || purge_tree_with(&target, options))
+                .await
+                .unwrap_or_else(|e| Err(vec![(tombstone, io::Error::other(e))]))
+        });
+        self.background.lock().unwrap().push(handle);
+    }
+
+    /// Wait for all background purges and return the paths in tombstones that could not be removed
+    pub async fn finish(&self) -> PurgeFailures {
+        let handles: Vec<_> = std::mem::take(&mut *self.background.lock().unwrap());
+        let mut failures = vec![];
+        for handle in handles {
+            match handle.await {
+                Ok(Ok(())) => {}
+                Ok(Err(failed)) => failures.extend(failed),
+                Err(e) => failures.push((PathBuf::new(), io::Error::other(e))),
+            }
+        }
+        failures
+    }
+
+    /// Purge tombstones left by a previous run; tombstones holding mount points were already reported and are skipped
+    pub fn recover(&self, mut tombstones: Vec<PathBuf>) {
+        tombstones.retain(|tombstone| !tombstone.join(MOUNTED_TOMBSTONE_MARKER).exists());
+        if tombstones.is_empty() {
+            return;
+        }
+        println!(
+            "{} Removing {} leftover tombstone(s) from a previous run",
+            "Info:".blue(),
+            tombstones.len()
+        );
+        for tombstone in tombstones {
+            self.schedule_purge(tombstone);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn make_tree(root: &Path) {
+        for d in 0..5 {
+            let dir = root.join(format!("pkg{}/lib/deep", d));
+            std::fs::create_dir_all(&dir).unwrap();
+            for f in 0..10 {
+                std::fs::write(dir.join(format!("f{}.js", f)), "x").unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_purge_tree_removes_everything() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path().join("node_modules");
+        make_tree(&target);
+
+        purge_tree(&target, 4).unwrap();
+        assert!(!target.exists());
+        assert!(dir.path().exists());
+    }
+
+    #[test]
+    fn test_purge_deep_and_wide_tree() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path().join("target");
+        let deep = (0..40).fold(target.clone(), |path, depth| path.join(format!("d{}", depth)));
+        std::fs::create_dir_all(&deep).unwrap();
+        std::fs::write(deep.join("lib.rlib"), "x").unwrap();
+        make_tree(&target.join("d0"));
+
+        purge_tree(&target, 8).unwrap();
+        assert!(!target.exists());
+    }
+
+    #[tokio::test]
+    async fn test_recover_skips_tombstones_with_mount_points() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mounted = dir.path().join(".rs_clean-tombstone-target-1-0");
+        let leftover = dir.path().join(".rs_clean-tombstone-target-1-1");
+        std::fs::create_dir_all(mounted.join("mnt")).unwrap();
+        std::fs::write(mounted.join(MOUNTED_TOMBSTONE_MARKER), "").unwrap();
+        make_tree(&leftover);
+
+        let deleter = Deleter::new(2);
+        deleter.recover(vec![mounted.clone(), leftover.clone()]);
+        assert!(deleter.finish().await.is_empty());
+        assert!(mounted.join("mnt").exists());
+        assert!(!leftover.exists());
+    }
+
+    #[test]
+    fn test_tombstone_path_is_in_project() {
+        let tombstone = tombstone_path(Path::new("/work/app/node_modules"), Path::new("/work/app")).unwrap();
+        assert_eq!(tombstone.parent(), Some(Path::new("/work/app")));
+        let tombstone = tombstone_path(Path::new("/work/app/target/debug"), Path::new("/work/app")).unwrap();
+        assert_eq!(tombstone.parent(), Some(Path::new("/work/app")));
+        assert!(is_tombstone(tombstone.file_name().unwrap()));
+        assert!(!is_tombstone(OsStr::new("node_modules")));
+    }
+
+    #[tokio::test]
+    async fn test_remove_renames_then_purges() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path().join("node_modules");
+        make_tree(&target);
+
+        let deleter = Deleter::new(2);
+        assert!(deleter.remove(&target, dir.path()).await.unwrap());
+        // 原路径立即消失
+        assert!(!target.exists());
+
+        assert!(deleter.finish().await.is_empty());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        assert!(!deleter.remove(&target, dir.path()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_remove_in_quarantine_mode_keeps_data() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path().join("venv");
+        make_tree(&target);
+
+        let quarantine = Quarantine::new(dir.path().join("q"), Duration::from_secs(60));
+        let deleter = Deleter::with_mode(2, DeleteMode::Quarantine(quarantine.clone()));
+        assert!(deleter.remove(&target, dir.path()).await.unwrap());
+        assert!(deleter.finish().await.is_empty());
+
+        assert!(!target.exists());
+        let entries = quarantine.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            deleter.disposal_of(&target),
+            Some(Disposal::Quarantined { entry: entries[0].0.clone() })
+        );
+        assert!(entries[0].0.join("data/pkg0/lib/deep/f0.js").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_purge_read_only_tree_with_fixed_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path().join("pkg/mod");
+        make_tree(&target);
+        // 与 Go 模块缓存一致：文件 0444，目录 0555
+        for entry in walkdir::WalkDir::new(&target).contents_first(true) {
+            let entry = entry.unwrap();
+            let mode = if entry.file_type().is_dir() { 0o555 } else { 0o444 };
+            std::fs::set_permissions(entry.path(), std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+
+        make_writable(&target.join("pkg0/lib/deep/f0.js")).unwrap();
+        let mode = std::fs::metadata(target.join("pkg0/lib/deep/f0.js")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+
+        let options = PurgeOptions { threads: 2, fix_permissions: true, ..Default::default() };
+        purge_tree_with(&target, options).unwrap();
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn test_summarize_failures() {
+        let failures = (0..5)
+            .map(|i| (PathBuf::from(format!("/t/f{}", i)), io::Error::from(io::ErrorKind::PermissionDenied)))
+            .collect();
+        let e = summarize_failures(failures);
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        assert!(e.to_string().starts_with("cannot remove /t/f0: "));
+        assert!(e.to_string().ends_with("; and 2 more"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_purge_tree_does_not_follow_symlinks() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = dir.path().join("store");
+        std::fs::create_dir(&store).unwrap();
+        std::fs::write(store.join("keep.txt"), "x").unwrap();
+
+        let target = dir.path().join("node_modules");
+        std::fs::create_dir(&target).unwrap();
+        std::os::unix::fs::symlink(&store, target.join("linked")).unwrap();
+
+        purge_tree(&target, 2).unwrap();
+        assert!(!target.exists());
+        assert!(store.join("keep.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_remove_unlinks_symlinked_artifact() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = dir.path().join("store");
+        make_tree(&store);
+        let target = dir.path().join("node_modules");
+
+        for deleter in [Deleter::new(2), Deleter::new(2).one_file_system(true)] {
+            std::os::unix::fs::symlink(&store, &target).unwrap();
+            assert!(deleter.remove(&target, dir.path()).await.unwrap());
+            assert!(deleter.finish().await.is_empty());
+            assert!(std::fs::symlink_metadata(&target).is_err());
+            assert!(store.join("pkg0/lib/deep/f0.js").exists());
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Build outputs of Go modules and the global caches (`go clean -cache` and friends)
+
+use crate::constant::{GORELEASER_CONFIGS, MAKEFILE_NAMES};
+use crate::ignore::IgnoreSet;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Cleaning options for Go projects (`[go]` in the config file)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GoOptions {
+    /// Global caches cleaned once at the end of each run
+    pub caches: Vec<GoCache>,
+    /// Remove the `bin/` and `dist/` output directories in modules
+    pub remove_outputs: bool,
+}
+
+/// Global caches that `go clean` can clean
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GoCache {
+    /// Build cache (`GOCACHE`)
+    Cache,
+    /// Cached test results
+    Testcache,
+    /// Module download cache (`GOMODCACHE`)
+    Modcache,
+    /// Fuzzing corpus cache (`GOCACHE/fuzz`)
+    Fuzzcache,
+}
+
+impl GoCache {
+    pub fn flag(&self) -> &'static str {
+        match self {
+            GoCache::Cache => "-cache",
+            GoCache::Testcache => "-testcache",
+            GoCache::Modcache => "-modcache",
+            GoCache::Fuzzcache => "-fuzzcache",
+        }
+    }
+}
+
+/// Build outputs in a module that can be removed
+///
+/// `dist/` is removed when a GoReleaser config exists and `bin/` when the Makefile refers to it;
+/// either is also removed when `.gitignore` ignores it. Directories of the same name checked into the repo (such as a `bin/` of scripts) are kept.
+pub fn build_outputs(dir: &Path) -> Vec<PathBuf> {
+    let makefile_uses_bin = MAKEFILE_NAMES
+        .iter()
+        .filter_map(|name| std::fs::read_to_string(dir.join(name)).ok())
+        .any(|content| content.contains("bin/"));
+    let has_goreleaser = GORELEASER_CONFIGS.iter().any(|name| dir.join(name).is_file());
+
+    let mut gitignores: Option<IgnoreSet> = None;
+    let mut outputs = vec![];
+    for (name, produced) in [("bin", makefile_uses_bin), ("dist", has_goreleaser)] {
+        let path = dir.join(name);
+        if !path.is_dir() {
+            continue;
+        }
+        if produced || gitignores.get_or_insert_with(|| IgnoreSet::gitignores(dir)).is_ignored(&path, true) {
+            outputs.push(path);
+        }
+    }
+    outputs
+}
+
+/// Directory reported by `go env <name>`; `None` when unset or not absolute (e.g. `GOCACHE=off`)
+async fn go_env(name: &str) -> Option<PathBuf> {
+    let output = Command::new("go").args(["env", name]).output().await.ok()?;
+    let value = String::from_utf8(output.stdout).ok()?;
+    let path = PathBuf::from(value.trim());
+    (output.status.success() && path.is_absolute()).then_some(path)
+}
+
+/// Directories holding the selected caches, for measuring freed space; nested directories keep only the outer one
+pub async fn cache_dirs(caches: &[GoCache]) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = vec![];
+    for cache in caches {
+        let dir = match cache {
+            GoCache::Modcache => go_env("GOMODCACHE").await,
+            GoCache::Fuzzcache => go_env("GOCACHE").await.map(|dir| dir.join("fuzz")),
+            GoCache::Cache | GoCache::Testcache => go_env("GOCACHE").await,
+        };
+        let Some(dir) = dir else {
+            continue;
+        };
+        if !dirs.iter().any(|listed| dir.starts_with(listed)) {
+            dirs.retain(|listed| !listed.starts_with(&dir));
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
+
+/// Run `go clean` once in `dir` to clean the selected global caches, returning the command line that ran
+///
+/// It runs in the module directory so the `toolchain` in `go.mod` picks the same Go version as builds do.
+pub async fn clean_caches(caches: &[GoCache], dir: &Path) -> io::Result<String> {
+    let flags: Vec<&str> = caches.iter().map(GoCache::flag).collect();
+    let command_line = format!("go clean {}", flags.join(" "));
+    let output = Command::new("go").arg("clean").args(&flags).current_dir(dir).output().await?;
+    if output.status.success() {
+        Ok(command_line)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("no output");
+        Err(io::Error::other(format!("`{}` failed ({}): {}", command_line, output.status, reason.trim())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_outputs_follow_conventions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        std::fs::create_dir(root.join("bin")).unwrap();
+        std::fs::create_dir(root.join("dist")).unwrap();
+        // 没有 Makefile、GoReleaser 配置或 .gitignore 规则时，`bin/` 可能是检入的脚本
+        assert!(build_outputs(root).is_empty());
+
+        std::fs::write(root.join(".goreleaser.yaml"), "version: 2\n").unwrap();
+        assert_eq!(build_outputs(root), vec![root.join("dist")]);
+
+        std::fs::write(root.join("Makefile"), "build:\n\tgo build -o bin/app ./cmd/app\n").unwrap();
+        assert_eq!(build_outputs(root), vec![root.join("bin"), root.join("dist")]);
+    }
+
+    #[test]
+    fn test_go_options_from_toml() {
+        let options: GoOptions = toml::from_str("caches = [\"cache\", \"modcache\"]\nremove_outputs = true").unwrap();
+        assert_eq!(options.caches, vec![GoCache::Cache, GoCache::Modcache]);
+        assert!(options.remove_outputs);
+    }
+}
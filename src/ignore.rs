@@ -0,0 +1,239 @@
+use crate::constant::IGNORE_FILE;
+use crate::os_path::glob_text;
+use glob::{MatchOptions, Pattern};
+use std::path::{Path, PathBuf};
+
+/// Same as gitignore: `*` doesn't cross `/` and matching is case-sensitive
+pub(crate) const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    pattern: Pattern,
+    /// `!pattern`: re-include a previously excluded path
+    negated: bool,
+    /// `pattern/`: match directories only
+    dir_only: bool,
+    /// Patterns containing `/` match relative to the ignore file's directory; others match a name at any depth
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    fn parse(line: &str, force_anchored: bool) -> Option<Self> {
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = force_anchored || line.contains('/');
+        let line = line.trim_start_matches('/');
+        if line.is_empty() {
+            return None;
+        }
+        let pattern = Pattern::new(line).ok()?;
+        Some(Self { pattern, negated, dir_only, anchored })
+    }
+}
+
+/// A single `.rs_cleanignore` file; patterns are relative to its directory
+#[derive(Debug, Clone)]
+pub struct IgnoreFile {
+    base: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreFile {
+    /// Parse gitignore-style content; invalid patterns are ignored
+    pub fn parse(base: &Path, content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| IgnoreRule::parse(line, false))
+            .collect();
+        Self { base: base.to_path_buf(), rules }
+    }
+
+    /// Build from command-line patterns; all of them match relative to `base`
+    pub fn anchored<S: AsRef<str>>(base: &Path, patterns: &[S]) -> Self {
+        let rules = patterns
+            .iter()
+            .filter_map(|pattern| IgnoreRule::parse(pattern.as_ref(), true))
+            .collect();
+        Self { base: base.to_path_buf(), rules }
+    }
+
+    /// Read `.rs_cleanignore` in `dir`
+    pub fn load(dir: &Path) -> Option<Self> {
+        Self::load_named(dir, IGNORE_FILE)
+    }
+
+    /// Read a gitignore-style file named `file_name` in `dir`
+    pub fn load_named(dir: &Path, file_name: &str) -> Option<Self> {
+        let content = std::fs::read_to_string(dir.join(file_name)).ok()?;
+        Some(Self::parse(dir, &content))
+    }
+
+    /// The last matching rule decides; returns `None` when no rule matches
+    fn matches(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        let relative = glob_text(relative.as_os_str()).replace('\\', "/");
+        if relative.is_empty() {
+            return None;
+        }
+        let name = relative.rsplit('/').next().unwrap_or(&relative);
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (!rule.dir_only || is_dir)
+                    && if rule.anchored {
+                        rule.pattern.matches_with(&relative, MATCH_OPTIONS)
+                    } else {
+                        rule.pattern.matches_with(name, MATCH_OPTIONS)
+                    }
+            })
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// All ignore files loaded during a scan
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreSet {
+    files: Vec<IgnoreFile>,
+}
+
+impl IgnoreSet {
+    /// Add ignore files in walk order; a parent directory's file must be added before its children's
+    pub fn push(&mut self, file: IgnoreFile) {
+        self.files.push(file);
+    }
+
+    /// Merge the rules of another tree; order doesn't matter when neither root contains the other
+    pub fn extend(&mut self, other: IgnoreSet) {
+        self.files.extend(other.files);
+    }
+
+    /// `.gitignore` files from the repository root down to `dir`; only `dir`'s own when it isn't in a git repository
+    pub fn gitignores(dir: &Path) -> Self {
+        let repo_root = dir.ancestors().find(|ancestor| ancestor.join(".git").exists());
+        let mut dirs: Vec<&Path> = match repo_root {
+            Some(root) => dir.ancestors().take_while(|ancestor| *ancestor != root).chain([root]).collect(),
+            None => vec![dir],
+        };
+        dirs.reverse();
+
+        let mut set = Self::default();
+        for dir in dirs {
+            if let Some(file) = IgnoreFile::load_named(dir, ".gitignore") {
+                set.push(file);
+            }
+        }
+        set
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Whether `path` is excluded by ignore files in its directory or its ancestors; deeper files take precedence
+    ///
+    /// As in gitignore, everything below an excluded directory is excluded and can't be re-included with `!`.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let parents: Vec<&Path> = path.ancestors().skip(1).collect();
+        parents.into_iter().rev().any(|dir| self.matches(dir, true)) || self.matches(path, is_dir)
+    }
+
+    /// Judge `path` by itself, without looking at its ancestors
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        self.files
+            .iter()
+            .rev()
+            .filter(|file| path.starts_with(&file.base))
+            .find_map(|file| file.matches(path, is_dir))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_file_patterns() {
+        let base = Path::new("/work");
+        let file = IgnoreFile::parse(
+            base,
+            "# release checkouts\nrelease-*/\n/web/dist\n*.egg-info\n!keep.egg-info\napps/**/build/\n",
+        );
+        let mut set = IgnoreSet::default();
+        set.push(file);
+
+        assert!(set.is_ignored(Path::new("/work/release-1.2"), true));
+        assert!(!set.is_ignored(Path::new("/work/release-1.2"), false));
+        assert!(set.is_ignored(Path::new("/work/web/dist"), true));
+        assert!(!set.is_ignored(Path::new("/work/other/web/dist"), true));
+        assert!(set.is_ignored(Path::new("/work/py/pkg.egg-info"), true));
+        assert!(!set.is_ignored(Path::new("/work/py/keep.egg-info"), true));
+        assert!(set.is_ignored(Path::new("/work/apps/a/b/build"), true));
+        assert!(!set.is_ignored(Path::new("/elsewhere/release-1.2"), true));
+        assert!(!set.is_ignored(base, true));
+    }
+
+    #[test]
+    fn test_anchored_patterns() {
+        let mut set = IgnoreSet::default();
+        set.push(IgnoreFile::anchored(Path::new("/work"), &["clients/legacy/*", "**/third_party/**", "dist"]));
+
+        assert!(set.is_ignored(Path::new("/work/clients/legacy/app"), true));
+        assert!(!set.is_ignored(Path::new("/work/clients/legacy"), true));
+        assert!(!set.is_ignored(Path::new("/work/clients/modern/app"), true));
+        assert!(set.is_ignored(Path::new("/work/a/third_party/lib"), true));
+        assert!(set.is_ignored(Path::new("/work/third_party/lib/src"), true));
+        // 不含 `/` 的模式同样相对于根目录
+        assert!(set.is_ignored(Path::new("/work/dist"), true));
+        assert!(!set.is_ignored(Path::new("/work/web/dist"), true));
+    }
+
+    #[test]
+    fn test_ignored_directory_covers_its_subtree() {
+        let mut set = IgnoreSet::default();
+        set.push(IgnoreFile::parse(Path::new("/work"), "legacy/\n!node_modules\n"));
+        set.push(IgnoreFile::anchored(Path::new("/work"), &["clients/old/*"]));
+
+        // 被排除目录中的路径不能被 `!` 重新包含
+        assert!(set.is_ignored(Path::new("/work/legacy/pkg/node_modules"), true));
+        assert!(set.is_ignored(Path::new("/work/clients/old/pkg/node_modules"), true));
+        assert!(!set.is_ignored(Path::new("/work/app/node_modules"), true));
+    }
+
+    #[test]
+    fn test_deeper_ignore_file_wins() {
+        let mut set = IgnoreSet::default();
+        set.push(IgnoreFile::parse(Path::new("/work"), "dist\n"));
+        set.push(IgnoreFile::parse(Path::new("/work/web"), "!dist\n"));
+
+        assert!(set.is_ignored(Path::new("/work/lib/dist"), true));
+        assert!(!set.is_ignored(Path::new("/work/web/dist"), true));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+        let mut set = IgnoreSet::default();
+        set.push(IgnoreFile::parse(Path::new("/work"), "release-?\n"));
+
+        let name = |bytes: &[u8]| Path::new("/work").join(std::ffi::OsStr::from_bytes(bytes));
+        // 一个无效字节对应一个 `?`
+        assert!(set.is_ignored(&name(b"release-\xFF"), true));
+        assert!(!set.is_ignored(&name(b"release-\xFF\xFE"), true));
+    }
+}
@@ -1,59 +1,100 @@
+pub mod cargo;
 pub mod cmd;
 pub mod config;
 pub mod constant;
+pub mod delete;
+pub mod go;
+pub mod ignore;
+pub mod manifest;
+pub mod node;
+pub mod os_path;
+pub mod quarantine;
+pub mod safety;
+pub mod scan;
+pub mod size;
+pub mod trash;
 pub mod utils;
 
 
-use crate::cmd::Cmd;
+use crate::cmd::{is_virtualenv, python_env_version, CleanOutcome, Cmd, CommandType};
+use crate::constant::{DEFAULT_HIDDEN_DENY_DIRS, KEEP_MARKER};
+use crate::ignore::{IgnoreFile, IgnoreSet};
+use crate::delete::{is_foreign_mount, is_tombstone, DeleteMode, Deleter, Disposal};
+use crate::go::GoCache;
+use crate::manifest::{ArtifactRecord, CacheRecord, History, ProjectRecord, RunManifest};
+use crate::node::{PackageJson, PackageManager, Workspace};
+use crate::utils::command_exists;
+use crate::size::{DirStats, HardLinkTracker, SizeBudget, SizeEngine};
 use colored::*;
 use futures::future;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::{fs, sync::Semaphore};
+use tokio::sync::Semaphore;
 use walkdir::WalkDir;
 
-async fn get_dir_size_async(path: &Path, max_depth: usize, max_files: usize) -> u64 {
-    use std::collections::VecDeque;
-
-    let mut total_size = 0;
-    let mut file_count = 0;
-    let mut dirs_to_visit = VecDeque::new();
-
-    if path.exists() {
-        dirs_to_visit.push_back((path.to_path_buf(), 0)); // (path, depth)
+/// Build artifacts of a project and their statistics
+#[derive(Debug, Clone, Default)]
+pub struct ProjectSize {
+    pub artifact_dirs: Vec<PathBuf>,
+    /// Statistics matching `artifact_dirs` one to one
+    pub artifact_stats: Vec<DirStats>,
+    pub stats: DirStats,
+}
 
-        while let Some((current_dir, depth)) = dirs_to_visit.pop_front() {
-            // 检查目录深度限制
-            if depth > max_depth {
-                eprintln!("{} Warning: Maximum directory depth ({}) exceeded for {}. Size calculation might be incomplete.",
-                         "SKIP".yellow(), max_depth, current_dir.display());
-                continue;
+impl ProjectSize {
+    /// Space freed by removing the given paths, taken from the statistics before cleaning
+    pub fn freed_by(&self, removed: &[PathBuf]) -> DirStats {
+        let mut freed = DirStats::default();
+        for (dir, stats) in self.artifact_dirs.iter().zip(&self.artifact_stats) {
+            if removed.contains(dir) {
+                freed.merge(stats);
             }
+        }
+        freed
+    }
+}
 
-            if let Ok(mut entries) = fs::read_dir(&current_dir).await {
-                while let Ok(Some(entry)) = entries.next_entry().await {
-                    // 检查文件数量限制
-                    if file_count > max_files {
-                        eprintln!("{} Warning: Maximum file count ({}) exceeded for {}. Size calculation might be incomplete.",
-                                 "SKIP".yellow(), max_files, current_dir.display());
-                        return total_size;
-                    }
+/// Measure the build artifacts of several projects in parallel; when the ecosystem declares no artifact paths, the whole project directory is measured
+async fn measure_projects(
+    engine: &SizeEngine,
+    projects: &[(&Cmd, &Path)],
+    ignores: &IgnoreSet,
+    project_budget: SizeBudget,
+    artifact_budget: SizeBudget,
+    hard_links: &Arc<HardLinkTracker>,
+) -> Vec<ProjectSize> {
+    let mut sizes = Vec::with_capacity(projects.len());
+    let mut roots = vec![];
+    let mut owners = vec![];
 
-                    if let Ok(metadata) = entry.metadata().await {
-                        if metadata.is_file() {
-                            total_size += metadata.len();
-                            file_count += 1;
-                        } else if metadata.is_dir() {
-                            dirs_to_visit.push_back((entry.path(), depth + 1));
-                        }
-                    }
-                }
+    for (index, (cmd, dir)) in projects.iter().enumerate() {
+        if cmd.declares_artifacts() {
+            let artifact_dirs = cmd.artifact_paths(dir, ignores);
+            for artifact in &artifact_dirs {
+                roots.push((artifact.clone(), artifact_budget));
+                owners.push(index);
             }
+            sizes.push(ProjectSize { artifact_dirs, ..Default::default() });
+        } else {
+            roots.push((dir.to_path_buf(), project_budget));
+            owners.push(index);
+            sizes.push(ProjectSize::default());
         }
     }
 
-    total_size
+    let results = engine.clone().measure_all_async(roots, Arc::clone(hard_links)).await;
+    for (owner, stats) in owners.into_iter().zip(results) {
+        let size = &mut sizes[owner];
+        size.stats.merge(&stats);
+        if size.artifact_stats.len() < size.artifact_dirs.len() {
+            size.artifact_stats.push(stats);
+        }
+    }
+    sizes
 }
 
 // 获取CPU逻辑核心数
@@ -63,26 +104,219 @@ pub fn get_cpu_core_count() -> usize {
         .unwrap_or(4) // 默认4个核心
 }
 
-pub async fn do_clean_all(
-    dir: &Path,
-    commands: &Vec<Cmd>,
-    exclude_dirs: &Vec<String>,
-    max_concurrent: Option<usize>,
-    max_directory_depth: usize,
-    max_files_per_project: usize,
-) -> u32 {
-    let entries: Vec<_> = WalkDir::new(dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_dir())
-        .collect();
+/// Result of scanning a directory tree
+#[derive(Debug, Default)]
+pub struct Discovery {
+    /// Detected project directories and their ecosystems
+    pub projects: Vec<(PathBuf, CommandType)>,
+    /// Tombstone directories left by a previous run
+    pub tombstones: Vec<PathBuf>,
+    /// Directories skipped because they contain a `.rs_clean_keep` marker
+    pub kept: Vec<PathBuf>,
+    /// `.rs_cleanignore` rules loaded during the walk
+    pub ignores: IgnoreSet,
+}
+
+/// How hidden directories (starting with `.`) are walked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HiddenPolicy {
+    /// Whether hidden directories are entered
+    pub include: bool,
+    /// Directory names skipped even when `include` is true
+    pub deny: Vec<String>,
+}
+
+impl Default for HiddenPolicy {
+    fn default() -> Self {
+        Self {
+            include: false,
+            deny: DEFAULT_HIDDEN_DENY_DIRS.map(String::from).to_vec(),
+        }
+    }
+}
+
+impl HiddenPolicy {
+    /// Whether a directory named `name` may be entered; non-hidden directories always may
+    pub fn allows(&self, name: &OsStr) -> bool {
+        if !name.as_encoded_bytes().starts_with(b".") {
+            return true;
+        }
+        self.include && !self.deny.iter().any(|denied| OsStr::new(denied) == name)
+    }
+}
+
+/// Directory walking options
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Exclude by directory name
+    pub exclude_dirs: Vec<String>,
+    /// Exclusion patterns matched relative to the root; matching subtrees and build artifacts are skipped
+    pub exclude: Vec<String>,
+    /// Inclusion patterns matched relative to the root; when not empty, only projects inside matching subtrees are cleaned
+    pub include: Vec<String>,
+    pub hidden: HiddenPolicy,
+    /// Follow directory symlinks (link cycles are detected)
+    pub follow_symlinks: bool,
+    /// Don't enter directories on a different device than the root, and don't remove build artifacts there
+    pub one_file_system: bool,
+}
+
+impl WalkOptions {
+    /// Whether the project directory or any of its ancestors matches an inclusion pattern
+    fn includes(&self, root: &Path, path: &Path, patterns: &[glob::Pattern]) -> bool {
+        if self.include.is_empty() {
+            return true;
+        }
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        relative
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| {
+                let ancestor = os_path::glob_text(ancestor.as_os_str()).replace('\\', "/");
+                patterns.iter().any(|p| p.matches_with(&ancestor, ignore::MATCH_OPTIONS))
+            })
+    }
+}
+
+/// A cleaning root and its walk options
+#[derive(Debug, Clone, Default)]
+pub struct CleanRoot {
+    pub path: PathBuf,
+    pub walk: WalkOptions,
+}
+
+impl CleanRoot {
+    pub fn new(path: impl Into<PathBuf>, walk: WalkOptions) -> Self {
+        Self { path: path.into(), walk }
+    }
+}
 
-    let cleaning_tasks: Vec<_> = entries
+/// Drop duplicate roots and roots inside other roots, keeping the original order
+///
+/// Roots are compared by canonical path; a covered root is handled by the outer root and its walk options.
+pub fn dedupe_roots(roots: Vec<CleanRoot>) -> Vec<CleanRoot> {
+    let canonical: Vec<PathBuf> = roots
         .iter()
+        .map(|root| {
+            root.path
+                .canonicalize()
+                .or_else(|_| std::path::absolute(&root.path))
+                .unwrap_or_else(|_| root.path.clone())
+        })
+        .collect();
+    roots
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| {
+            let path = &canonical[*index];
+            !canonical.iter().enumerate().any(|(other, outer)| {
+                other != *index && path.starts_with(outer) && (path != outer || other < *index)
+            })
+        })
+        .map(|(_, root)| root)
+        .collect()
+}
+
+/// Scan every root and merge the results, also returning the index of the root each project belongs to
+fn discover_roots(roots: &[CleanRoot], commands: &[Cmd]) -> (Discovery, Vec<usize>) {
+    let mut merged = Discovery::default();
+    let mut owners = vec![];
+    for (index, root) in roots.iter().enumerate() {
+        let discovery = discover(&root.path, commands, &root.walk);
+        owners.extend(std::iter::repeat(index).take(discovery.projects.len()));
+        merged.projects.extend(discovery.projects);
+        merged.tombstones.extend(discovery.tombstones);
+        merged.kept.extend(discovery.kept);
+        merged.ignores.extend(discovery.ignores);
+    }
+    // 不同根目录下的项目也可能共用同一个 Cargo 输出目录
+    if roots.len() > 1 {
+        let keep = shared_target_filter(&merged.projects);
+        let mut flags = keep.iter();
+        owners.retain(|_| *flags.next().unwrap_or(&true));
+        let mut flags = keep.iter();
+        merged.projects.retain(|_| *flags.next().unwrap_or(&true));
+    }
+    (merged, owners)
+}
+
+/// Scan a directory tree and return the detected project directories and their ecosystems
+pub fn find_projects(dir: &Path, commands: &[Cmd], walk: &WalkOptions) -> Vec<(PathBuf, CommandType)> {
+    discover(dir, commands, walk).projects
+}
+
+/// Scan a directory tree, also collecting leftover tombstone directories (without entering them)
+///
+/// Directories containing `.rs_clean_keep`, directories excluded by name, hidden directories (unless `--hidden` is on)
+/// and subtrees excluded by `--exclude` or `.rs_cleanignore` are not walked.
+pub fn discover(dir: &Path, commands: &[Cmd], walk: &WalkOptions) -> Discovery {
+    let mut tombstones = vec![];
+    let mut kept = vec![];
+    let mut ignores = IgnoreSet::default();
+    if !walk.exclude.is_empty() {
+        ignores.push(IgnoreFile::anchored(dir, &walk.exclude));
+    }
+    let include: Vec<_> = walk.include.iter().filter_map(|p| glob::Pattern::new(p).ok()).collect();
+    let mut seen = HashSet::new();
+    let projects = WalkDir::new(dir)
+        .follow_links(walk.follow_symlinks)
+        .same_file_system(walk.one_file_system)
+        .into_iter()
+        .filter_entry(|e| {
+            if !e.file_type().is_dir() {
+                return true;
+            }
+            // 根目录本身（例如 `.`）不受目录名规则限制
+            if e.depth() > 0 {
+                // 墓碑以 `.` 开头，但无论隐藏目录策略如何都不会进入
+                if is_tombstone(e.file_name()) {
+                    tombstones.push(e.path().to_path_buf());
+                    return false;
+                }
+                if !walk.hidden.allows(e.file_name())
+                    || walk.exclude_dirs.iter().any(|name| OsStr::new(name) == e.file_name())
+                    || ignores.is_ignored(e.path(), true)
+                {
+                    return false;
+                }
+            }
+            if e.path().join(KEEP_MARKER).exists() {
+                kept.push(e.path().to_path_buf());
+                return false;
+            }
+            // 先序遍历保证父目录的规则先于子目录加入
+            if let Some(file) = IgnoreFile::load(e.path()) {
+                ignores.push(file);
+            }
+            true
+        })
+        .filter_map(|e| match e {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                if let (Some(path), Some(ancestor)) = (e.path(), e.loop_ancestor()) {
+                    eprintln!(
+                        "{} Skipping symlink loop: {} -> {}",
+                        "Warning:".yellow(),
+                        path.display(),
+                        ancestor.display()
+                    );
+                }
+                None
+            }
+        })
+        .filter(|e| e.file_type().is_dir())
         .filter_map(|entry| {
             let path = entry.path();
-            if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
-                if dir_name.starts_with('.') || exclude_dirs.contains(&dir_name.to_string()) {
+            if !walk.includes(dir, path, &include) {
+                return None;
+            }
+            // 跟随符号链接时同一个项目可能经由多条路径到达
+            if walk.follow_symlinks {
+                let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+                if !seen.insert(canonical) {
                     return None;
                 }
             }
@@ -105,9 +339,117 @@ pub async fn do_clean_all(
         })
         .flatten()
         .collect();
+    let projects = without_workspace_members(projects, commands);
+    let mut keep = shared_target_filter(&projects).into_iter();
+    let projects = projects.into_iter().filter(|_| keep.next().unwrap_or(true)).collect();
+
+    Discovery { projects, tombstones, kept, ignores }
+}
+
+/// Drop member packages of Node.js workspaces: the workspace root cleans them all
+fn without_workspace_members(projects: Vec<(PathBuf, CommandType)>, commands: &[Cmd]) -> Vec<(PathBuf, CommandType)> {
+    let Some(node) = commands.iter().find(|cmd| cmd.command_type == CommandType::NodeJs) else {
+        return projects;
+    };
+    let members: HashSet<PathBuf> = projects
+        .iter()
+        .filter(|(_, cmd_type)| *cmd_type == CommandType::NodeJs)
+        .filter_map(|(path, _)| Workspace::detect(path, &node.exclude_dirs))
+        .flat_map(|workspace| workspace.members)
+        .collect();
+    if members.is_empty() {
+        return projects;
+    }
+    projects
+        .into_iter()
+        .filter(|(path, cmd_type)| *cmd_type != CommandType::NodeJs || !members.contains(path))
+        .collect()
+}
+
+/// Whether each project is kept: of the projects sharing one Cargo target directory (such as workspace members) only the first is kept,
+/// which in a pre-order walk is usually the workspace root
+fn shared_target_filter(projects: &[(PathBuf, CommandType)]) -> Vec<bool> {
+    let mut targets = HashSet::new();
+    projects
+        .iter()
+        .map(|(path, cmd_type)| {
+            if *cmd_type != CommandType::Cargo {
+                return true;
+            }
+            let target = cargo::target_dir(path);
+            targets.insert(target.canonicalize().unwrap_or(target))
+        })
+        .collect()
+}
+
+/// Options for a cleaning run
+#[derive(Debug, Clone)]
+pub struct CleanOptions {
+    pub max_concurrent: Option<usize>,
+    pub project_budget: SizeBudget,
+    pub artifact_budget: SizeBudget,
+    /// Whether sizes are re-measured after an external command cleaned
+    pub verify_size: bool,
+    pub delete_mode: DeleteMode,
+    /// When removal is denied, make read-only files and directories writable and retry
+    pub fix_permissions: bool,
+    /// Where the run manifest is saved; nothing is recorded when empty
+    pub history: Option<History>,
+}
+
+impl Default for CleanOptions {
+    fn default() -> Self {
+        Self {
+            max_concurrent: None,
+            project_budget: SizeBudget::UNLIMITED,
+            artifact_budget: SizeBudget::UNLIMITED,
+            verify_size: true,
+            delete_mode: DeleteMode::Permanent,
+            fix_permissions: false,
+            history: None,
+        }
+    }
+}
+
+pub async fn do_clean_all(roots: &[CleanRoot], commands: &[Cmd], options: CleanOptions) -> u32 {
+    let CleanOptions {
+        max_concurrent,
+        project_budget,
+        artifact_budget,
+        verify_size,
+        delete_mode,
+        fix_permissions,
+        history,
+    } = options;
+    // 清单中记录绝对路径，保证在其它目录下执行 `undo` 也能找到原位置
+    let roots: Vec<CleanRoot> = roots
+        .iter()
+        .map(|root| CleanRoot {
+            path: std::path::absolute(&root.path).unwrap_or_else(|_| root.path.clone()),
+            walk: root.walk.clone(),
+        })
+        .collect();
+    let root_paths: Vec<PathBuf> = roots.iter().map(|root| root.path.clone()).collect();
+    let mut manifest = RunManifest::new(&root_paths);
+    let (Discovery { projects: cleaning_tasks, tombstones, kept, ignores }, owners) = discover_roots(&roots, commands);
+    report_kept(&kept);
+
+    // 使用配置的并发限制或默认值
+    let max_concurrent_limit = max_concurrent.unwrap_or_else(get_cpu_core_count);
+    let freed_label = match &delete_mode {
+        DeleteMode::Permanent => "Total space freed",
+        DeleteMode::Trash(_) => "Total space moved to trash",
+        DeleteMode::Quarantine(_) => "Total space moved to quarantine",
+    };
+    let one_file_system = roots.iter().any(|root| root.walk.one_file_system);
+    let deleter = Deleter::with_mode(max_concurrent_limit, delete_mode)
+        .one_file_system(one_file_system)
+        .fix_permissions(fix_permissions);
+    deleter.recover(tombstones);
 
     if cleaning_tasks.is_empty() {
         println!("{}", "No projects found to clean".yellow());
+        report_purge_failures(deleter.finish().await, fix_permissions);
         return 0;
     }
 
@@ -124,39 +466,43 @@ pub async fn do_clean_all(
 
     pb.set_message("Scanning projects...");
 
-    // 使用配置的并发限制或默认值
-    let max_concurrent_limit = max_concurrent.unwrap_or_else(get_cpu_core_count);
     let semaphore = Arc::new(Semaphore::new(max_concurrent_limit));
     
-    // 并行计算所有项目的初始大小（带并发限制）
-    let size_futures: Vec<_> = cleaning_tasks
+    // 清理后的复查单独去重硬链接，保证前后两次统计口径一致
+    let engine = SizeEngine::new(max_concurrent_limit);
+    let links_before = Arc::new(HardLinkTracker::default());
+    let links_after = Arc::new(HardLinkTracker::default());
+
+    // 在同一个线程池中并行统计所有项目的初始大小
+    let projects: Vec<(&Cmd, &Path)> = cleaning_tasks
         .iter()
-        .map(|(path, _)| {
-            let semaphore = Arc::clone(&semaphore);
-            async move {
-                let _permit = semaphore.acquire().await.unwrap();
-                get_dir_size_async(path, max_directory_depth, max_files_per_project).await
-            }
+        .map(|(path, cmd_name)| {
+            let cmd = commands.iter().find(|c| c.command_type == *cmd_name).unwrap();
+            (cmd, path.as_path())
         })
         .collect();
+    let sizes_before = measure_projects(&engine, &projects, &ignores, project_budget, artifact_budget, &links_before).await;
+    let mut total_before = DirStats::default();
+    sizes_before.iter().for_each(|size| total_before.merge(&size.stats));
 
-    let sizes_before = future::join_all(size_futures).await;
-    let total_size_before: u64 = sizes_before.iter().sum();
-
-    if total_size_before > 0 {
+    if total_before.disk_size > 0 {
         pb.set_message(format!(
             "Total cache size: {}",
-            format_size(total_size_before)
+            format_size(total_before.disk_size)
         ));
     }
 
     // 准备并行执行的任务（带并发限制）
     let cleaning_futures: Vec<_> = cleaning_tasks
         .into_iter()
-        .zip(sizes_before.into_iter())
+        .zip(sizes_before)
         .map(|((path, cmd_name), size_before)| {
             let pb = Arc::clone(&pb);
             let semaphore = Arc::clone(&semaphore);
+            let links_after = &links_after;
+            let deleter = &deleter;
+            let ignores = &ignores;
+            let engine = &engine;
 
             async move {
                 let _permit = semaphore.acquire().await.unwrap();
@@ -164,27 +510,100 @@ pub async fn do_clean_all(
                 pb.set_message(format!("Cleaning {} ({})", path.display(), cmd_name.as_str()));
 
                 let cmd = commands.iter().find(|c| c.command_type == cmd_name).unwrap();
-                match cmd.run_clean(&path).await {
-                    Ok(_) => {
-                        let size_after = get_dir_size_async(&path, max_directory_depth, max_files_per_project).await;
-                        let cleaned_size = size_before.saturating_sub(size_after);
+                let mut record = ProjectRecord {
+                    path: path.clone(),
+                    ecosystem: cmd_name.as_str().to_string(),
+                    ..Default::default()
+                };
+                // 删除前读取虚拟环境的解释器版本
+                let envs: Vec<(PathBuf, Option<String>)> = size_before
+                    .artifact_dirs
+                    .iter()
+                    .filter(|dir| is_virtualenv(dir))
+                    .map(|dir| (dir.clone(), python_env_version(dir)))
+                    .collect();
+                match cmd.run_clean(&path, deleter, engine, ignores).await {
+                    Ok(outcome) => {
+                        let mut failed = vec![];
+                        let mut profiles = vec![];
+                        // 自己删除的目录直接使用清理前的统计；外部命令才需要重新统计
+                        let freed = match outcome {
+                            CleanOutcome::Kept(reason) => {
+                                // 未清理的项目不计入清理数量，也不写入清单
+                                pb.println(kept_line(&path, &reason));
+                                return (0, Some(DirStats::default()), None);
+                            }
+                            CleanOutcome::Removed(removed) => {
+                                record.artifacts = artifact_records(&size_before, &removed, deleter);
+                                Some(size_before.freed_by(&removed))
+                            }
+                            CleanOutcome::Partial { removed, failed: not_removed } => {
+                                record.artifacts = artifact_records(&size_before, &removed, deleter);
+                                record.error = Some(summarize_failures(&not_removed));
+                                failed = not_removed;
+                                Some(size_before.freed_by(&removed))
+                            }
+                            CleanOutcome::Swept { removed, failed: not_removed, profiles: swept_profiles } => {
+                                // 删除的是输出目录内部的路径，使用删除前的统计
+                                let (paths, stats): (Vec<PathBuf>, Vec<DirStats>) = removed.into_iter().unzip();
+                                let swept = ProjectSize { artifact_dirs: paths, artifact_stats: stats, ..Default::default() };
+                                record.artifacts = artifact_records(&swept, &swept.artifact_dirs, deleter);
+                                if !not_removed.is_empty() {
+                                    record.error = Some(summarize_failures(&not_removed));
+                                }
+                                failed = not_removed;
+                                profiles = swept_profiles;
+                                Some(swept.freed_by(&swept.artifact_dirs))
+                            }
+                            CleanOutcome::External(command) if verify_size => {
+                                record.external_command = Some(command);
+                                let size_after = measure_projects(engine, &[(cmd, &path)], ignores, project_budget, artifact_budget, links_after)
+                                    .await
+                                    .pop()
+                                    .unwrap_or_default()
+                                    .stats;
+                                Some(size_before.stats.saturating_sub(&size_after))
+                            }
+                            CleanOutcome::External(command) => {
+                                record.external_command = Some(command);
+                                None
+                            }
+                        };
+                        record.freed_bytes = freed.as_ref().map(|f| f.disk_size);
+                        record.apparent_bytes = freed.as_ref().map(|f| f.size);
 
-                        if cleaned_size > 0 {
-                            pb.println(format!(
-                                "✓ {} {} - {}",
-                                "Cleaned".green(),
-                                path.display(),
-                                format_size(cleaned_size).cyan()
-                            ));
+                        let detail = match freed {
+                            Some(freed) if freed.disk_size > 0 => format_size(freed.disk_size).cyan(),
+                            Some(_) => "No files removed".yellow(),
+                            None => "Size not verified".yellow(),
+                        };
+                        let status = if failed.is_empty() {
+                            format!("✓ {}", "Cleaned".green())
                         } else {
+                            format!("⚠ {}", "Partially cleaned".yellow())
+                        };
+                        pb.println(format!("{} {} - {}", status, path.display(), detail));
+                        for (artifact, reason) in &failed {
+                            pb.println(format!("    {} {} - {}", "✗".red(), artifact.display(), reason));
+                        }
+                        for profile in &profiles {
                             pb.println(format!(
-                                "✓ {} {} - {}",
-                                "Cleaned".green(),
-                                path.display(),
-                                "No files removed".yellow()
+                                "    {} {} (removed {})",
+                                profile.path.strip_prefix(&path).unwrap_or(&profile.path).display(),
+                                format_size(profile.size.disk_size),
+                                format_size(profile.removed.disk_size)
                             ));
                         }
-                        (1, size_before, size_after)
+                        for (env, version) in &envs {
+                            if record.artifacts.iter().any(|artifact| &artifact.path == env) {
+                                pb.println(format!(
+                                    "    Removed virtualenv {} (Python {})",
+                                    env.display(),
+                                    version.as_deref().unwrap_or("unknown")
+                                ));
+                            }
+                        }
+                        (1, freed, Some(record))
                     }
                     Err(e) => {
                         pb.println(format!(
@@ -194,7 +613,8 @@ pub async fn do_clean_all(
                             cmd_name.as_str(),
                             e
                         ));
-                        (0, size_before, 0)
+                        record.error = Some(e.to_string());
+                        (0, Some(DirStats::default()), Some(record))
                     }
                 }
             }
@@ -206,22 +626,240 @@ pub async fn do_clean_all(
 
     pb.finish_with_message("Cleaning complete!");
 
+    // 项目已经呈现为清理完成，等待后台删除墓碑
+    report_purge_failures(deleter.finish().await, fix_permissions);
+
+    if commands.iter().any(|cmd| cmd.command_type == CommandType::NodeJs && cmd.node.prune) {
+        let node_projects = results
+            .iter()
+            .filter(|(count, _, _)| *count > 0)
+            .filter_map(|(_, _, record)| record.as_ref())
+            .filter(|record| record.ecosystem == CommandType::NodeJs.as_str())
+            .map(|record| record.path.as_path());
+        prune_package_caches(node_projects).await;
+    }
+
+    // Go 的全局缓存与模块无关，每次运行只清理一次
+    let go_caches = commands
+        .iter()
+        .find(|cmd| cmd.command_type == CommandType::Go)
+        .map(|cmd| cmd.go.caches.as_slice())
+        .unwrap_or_default();
+    if !go_caches.is_empty() {
+        let first_module = results
+            .iter()
+            .filter(|(count, _, _)| *count > 0)
+            .filter_map(|(_, _, record)| record.as_ref())
+            .find(|record| record.ecosystem == CommandType::Go.as_str())
+            .map(|record| record.path.as_path());
+        if let Some(module) = first_module {
+            manifest.global_caches.extend(clean_go_caches(go_caches, module, &engine).await);
+        }
+    }
+
     // 计算总结果
     let total_cleaned: u32 = results.iter().map(|(count, _, _)| count).sum();
-    let total_size_after: u64 = results.iter().map(|(_, _, after)| after).sum();
-    let total_freed = total_size_before.saturating_sub(total_size_after);
+    let mut total_freed = DirStats::default();
+    results.iter().filter_map(|(_, freed, _)| freed.as_ref()).for_each(|freed| total_freed.merge(freed));
+    let unverified = results.iter().filter(|(_, freed, _)| freed.is_none()).count();
 
-    if total_size_before > 0 {
+    if roots.len() > 1 {
+        report_root_subtotals(&root_paths, &owners, &results);
+    }
+
+    if total_before.disk_size > 0 {
         println!(
-            "Total space freed: {}",
-            format_size(total_freed).green().bold()
+            "{}: {} on disk ({} apparent)",
+            freed_label,
+            format_size(total_freed.disk_size).green().bold(),
+            format_size(total_freed.size)
         );
+        if unverified > 0 {
+            println!(
+                "{} {} project(s) cleaned by external commands were not re-measured",
+                "Note:".yellow(),
+                unverified
+            );
+        }
     }
 
+    if !manifest.global_caches.is_empty() {
+        let (disk, apparent) = manifest
+            .global_caches
+            .iter()
+            .fold((0, 0), |(disk, apparent), cache| (disk + cache.freed_bytes, apparent + cache.apparent_bytes));
+        println!(
+            "Global caches freed: {} on disk ({} apparent)",
+            format_size(disk).green().bold(),
+            format_size(apparent)
+        );
+    }
+
+    manifest.projects = results.into_iter().filter_map(|(_, _, record)| record).collect();
+    save_manifest(history.as_ref(), manifest);
+
     total_cleaned
 }
 
-fn format_size(bytes: u64) -> String {
+/// Artifacts that could not be removed and why, written to the manifest's `error`
+fn summarize_failures(failed: &[(PathBuf, String)]) -> String {
+    failed
+        .iter()
+        .map(|(path, reason)| format!("{}: {}", path.display(), reason))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Manifest entries for the removed artifacts, from the statistics before cleaning and the disposals recorded by the deleter
+fn artifact_records(size: &ProjectSize, removed: &[PathBuf], deleter: &Deleter) -> Vec<ArtifactRecord> {
+    removed
+        .iter()
+        .map(|path| {
+            let stats = size
+                .artifact_dirs
+                .iter()
+                .position(|dir| dir == path)
+                .and_then(|index| size.artifact_stats.get(index))
+                .cloned()
+                .unwrap_or_default();
+            ArtifactRecord {
+                path: path.clone(),
+                disk_bytes: stats.disk_size,
+                apparent_bytes: stats.size,
+                disposal: deleter.disposal_of(path).unwrap_or(Disposal::Deleted),
+            }
+        })
+        .collect()
+}
+
+/// With several roots, sum up the freed space per root
+fn report_root_subtotals(roots: &[PathBuf], owners: &[usize], results: &[(u32, Option<DirStats>, Option<ProjectRecord>)]) {
+    println!("Per root:");
+    for (index, root) in roots.iter().enumerate() {
+        let mut freed = DirStats::default();
+        let mut cleaned = 0;
+        for ((count, stats, _), _) in results.iter().zip(owners).filter(|(_, owner)| **owner == index) {
+            cleaned += count;
+            if let Some(stats) = stats {
+                freed.merge(stats);
+            }
+        }
+        println!(
+            "  {} - {} ({} project{})",
+            root.display(),
+            format_size(freed.disk_size).cyan(),
+            cleaned,
+            if cleaned == 1 { "" } else { "s" }
+        );
+    }
+}
+
+fn save_manifest(history: Option<&History>, mut manifest: RunManifest) {
+    let Some(history) = history else {
+        return;
+    };
+    manifest.finished_at = utils::unix_now();
+    match history.save(&manifest) {
+        Ok(_) => println!(
+            "Run recorded as {} (see `rs_clean history`, revert with `rs_clean undo`)",
+            manifest.id.cyan()
+        ),
+        Err(e) => eprintln!(
+            "{} Failed to write run manifest to {}: {}",
+            "Warning:".yellow(),
+            history.dir().display(),
+            e
+        ),
+    }
+}
+
+/// Run the cache cleanup command once for each package manager used by the cleaned Node.js projects
+///
+/// The command runs in the first project using that package manager, so tools like Yarn that pick a version per project use the right one.
+/// Shared caches don't count towards the freed space.
+async fn prune_package_caches<'a>(projects: impl Iterator<Item = &'a Path>) {
+    let mut managers: Vec<(PackageManager, &Path)> = vec![];
+    for dir in projects {
+        let package = PackageJson::load(dir).unwrap_or_default();
+        if let Some(manager) = PackageManager::detect(dir, &package) {
+            if manager.prune_command().is_some() && !managers.iter().any(|(seen, _)| *seen == manager) {
+                managers.push((manager, dir));
+            }
+        }
+    }
+
+    for (manager, dir) in managers {
+        let program = manager.prune_command().and_then(|command| command.first()).copied().unwrap_or_default();
+        if !command_exists(program) {
+            println!("{} {} not found, skipping its cache prune", "Note:".yellow(), program);
+            continue;
+        }
+        match manager.prune(dir).await {
+            Ok(command) => println!("✓ {} {} cache ({})", "Pruned".green(), manager.as_str(), command),
+            Err(e) => eprintln!("{} Failed to prune {} cache: {}", "Warning:".yellow(), manager.as_str(), e),
+        }
+    }
+}
+
+/// Run `go clean` once in `module` to clean the global caches; the freed space is the difference between two measurements of the cache directories
+async fn clean_go_caches(caches: &[GoCache], module: &Path, engine: &SizeEngine) -> Option<CacheRecord> {
+    let dirs = go::cache_dirs(caches).await;
+    let measure = || {
+        let roots = dirs.iter().map(|dir| (dir.clone(), SizeBudget::UNLIMITED)).collect();
+        engine.clone().measure_all_async(roots, Arc::new(HardLinkTracker::default()))
+    };
+    let mut before = DirStats::default();
+    measure().await.iter().for_each(|stats| before.merge(stats));
+
+    match go::clean_caches(caches, module).await {
+        Ok(command) => {
+            let mut after = DirStats::default();
+            measure().await.iter().for_each(|stats| after.merge(stats));
+            let freed = before.saturating_sub(&after);
+            println!("✓ {} Go caches ({}) - {}", "Cleaned".green(), command, format_size(freed.disk_size).cyan());
+            Some(CacheRecord {
+                command,
+                paths: dirs,
+                freed_bytes: freed.disk_size,
+                apparent_bytes: freed.size,
+            })
+        }
+        Err(e) => {
+            eprintln!("{} Failed to clean Go caches: {}", "Warning:".yellow(), e);
+            None
+        }
+    }
+}
+
+fn report_kept(kept: &[PathBuf]) {
+    for path in kept {
+        println!("{}", kept_line(path, &format!("found {}", KEEP_MARKER)));
+    }
+}
+
+/// A project that was not cleaned, and why
+fn kept_line(path: &Path, reason: &str) -> String {
+    format!("{} {} ({})", "Kept".blue(), path.display(), reason)
+}
+
+fn report_purge_failures(failures: Vec<(PathBuf, std::io::Error)>, fix_permissions: bool) {
+    // 每个失败的路径单独报告，数量过多时只列出前面的部分
+    const MAX_REPORTED: usize = 10;
+    for (path, e) in failures.iter().take(MAX_REPORTED) {
+        eprintln!("{} Failed to remove {}: {}", "Warning:".yellow(), path.display(), e);
+    }
+    if failures.len() > MAX_REPORTED {
+        eprintln!("{} ... and {} more path(s)", "Warning:".yellow(), failures.len() - MAX_REPORTED);
+    }
+    if failures.iter().any(|(_, e)| !is_foreign_mount(e)) {
+        eprintln!("{} Leftover files are retried on the next run", "Note:".yellow());
+    }
+    if !fix_permissions && failures.iter().any(|(_, e)| e.kind() == std::io::ErrorKind::PermissionDenied) {
+        eprintln!("{} Pass --fix-permissions to make read-only files writable and retry", "Hint:".yellow());
+    }
+}
+
+pub fn format_size(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     let mut size = bytes as f64;
     let mut unit_index = 0;
@@ -237,3 +875,347 @@ fn format_size(bytes: u64) -> String {
         format!("{:.2} {}", size, UNITS[unit_index])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_measure_projects_counts_only_artifacts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("target/debug/deps/a/b/c/d/e")).unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+        std::fs::write(dir.path().join("main.rs"), vec![0u8; 1000]).unwrap();
+        std::fs::write(dir.path().join("target/debug/deps/a/b/c/d/e/lib.rlib"), vec![0u8; 64]).unwrap();
+
+        let cmd = Cmd::new(CommandType::Cargo, vec!["Cargo.toml"]);
+        let project_budget = SizeBudget { max_depth: 1, max_files: 1 };
+        let links = Arc::new(HardLinkTracker::default());
+        let sizes = measure_projects(
+            &SizeEngine::new(2),
+            &[(&cmd, dir.path())],
+            &IgnoreSet::default(),
+            project_budget,
+            SizeBudget::UNLIMITED,
+            &links,
+        )
+        .await;
+
+        assert_eq!(sizes[0].artifact_dirs, vec![dir.path().join("target")]);
+        assert_eq!(sizes[0].stats.size, 64);
+        assert_eq!(sizes[0].stats.file_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_measure_projects_falls_back_to_project_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("go.mod"), vec![0u8; 10]).unwrap();
+        std::fs::write(dir.path().join("app"), vec![0u8; 90]).unwrap();
+
+        let cmd = Cmd::new(CommandType::Go, vec!["go.mod"]);
+        let links = Arc::new(HardLinkTracker::default());
+        let sizes = measure_projects(
+            &SizeEngine::new(2),
+            &[(&cmd, dir.path())],
+            &IgnoreSet::default(),
+            SizeBudget::UNLIMITED,
+            SizeBudget::UNLIMITED,
+            &links,
+        )
+        .await;
+
+        assert!(sizes[0].artifact_dirs.is_empty());
+        assert_eq!(sizes[0].stats.size, 100);
+    }
+
+    #[tokio::test]
+    async fn test_freed_by_uses_measured_artifacts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules")).unwrap();
+        std::fs::create_dir_all(dir.path().join("dist")).unwrap();
+        std::fs::write(dir.path().join("package.json"), r#"{"devDependencies":{"vite":"^5"}}"#).unwrap();
+        std::fs::write(dir.path().join("node_modules/a.js"), vec![0u8; 30]).unwrap();
+        std::fs::write(dir.path().join("dist/b.js"), vec![0u8; 12]).unwrap();
+
+        let cmd = Cmd::new(CommandType::NodeJs, vec!["package.json"]);
+        let links = Arc::new(HardLinkTracker::default());
+        let sizes = measure_projects(
+            &SizeEngine::new(2),
+            &[(&cmd, dir.path())],
+            &IgnoreSet::default(),
+            SizeBudget::UNLIMITED,
+            SizeBudget::UNLIMITED,
+            &links,
+        )
+        .await;
+
+        let freed = sizes[0].freed_by(&[dir.path().join("dist")]);
+        assert_eq!(freed.size, 12);
+        assert_eq!(sizes[0].freed_by(&[]).size, 0);
+    }
+
+    #[test]
+    fn test_discover_collects_tombstones() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let project = dir.path().join("web");
+        let tombstone = project.join(".rs_clean-tombstone-node_modules-1-0");
+        std::fs::create_dir_all(tombstone.join("left-pad")).unwrap();
+        std::fs::write(project.join("package.json"), "{}").unwrap();
+        std::fs::write(tombstone.join("left-pad/package.json"), "{}").unwrap();
+
+        let commands = vec![Cmd::new(CommandType::NodeJs, vec!["package.json"])];
+        let discovery = discover(dir.path(), &commands, &WalkOptions::default());
+        assert_eq!(discovery.projects, vec![(project, CommandType::NodeJs)]);
+        assert_eq!(discovery.tombstones, vec![tombstone]);
+    }
+
+    #[test]
+    fn test_discover_honors_keep_marker_and_ignore_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for project in ["release", "main", "vendor-fork/lib", "web"] {
+            std::fs::create_dir_all(dir.path().join(project)).unwrap();
+            std::fs::write(dir.path().join(project).join("Cargo.toml"), "[package]").unwrap();
+        }
+        std::fs::write(dir.path().join("release/.rs_clean_keep"), "").unwrap();
+        std::fs::write(dir.path().join(".rs_cleanignore"), "vendor-*/\n").unwrap();
+        std::fs::write(dir.path().join("web/.rs_cleanignore"), "target\n").unwrap();
+
+        let commands = vec![Cmd::new(CommandType::Cargo, vec!["Cargo.toml"])];
+        let mut discovery = discover(dir.path(), &commands, &WalkOptions::default());
+        discovery.projects.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            discovery.projects,
+            vec![
+                (dir.path().join("main"), CommandType::Cargo),
+                (dir.path().join("web"), CommandType::Cargo),
+            ]
+        );
+        assert_eq!(discovery.kept, vec![dir.path().join("release")]);
+        assert!(discovery.ignores.is_ignored(&dir.path().join("web/target"), true));
+        assert!(!discovery.ignores.is_ignored(&dir.path().join("main/target"), true));
+    }
+
+    #[test]
+    fn test_discover_hidden_policy() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for project in [".worktrees/feature", ".config/tool", ".git/modules/sub", "web", "web/node_modules/left-pad"] {
+            std::fs::create_dir_all(dir.path().join(project)).unwrap();
+            std::fs::write(dir.path().join(project).join("package.json"), "{}").unwrap();
+        }
+        let tombstone = dir.path().join("web/.rs_clean-tombstone-dist-1-0");
+        std::fs::create_dir_all(&tombstone).unwrap();
+        std::fs::write(tombstone.join("package.json"), "{}").unwrap();
+
+        let commands = vec![Cmd::new(CommandType::NodeJs, vec!["package.json"])];
+        let found = |walk: &WalkOptions| {
+            let mut discovery = discover(dir.path(), &commands, walk);
+            discovery.projects.sort_by(|a, b| a.0.cmp(&b.0));
+            let paths: Vec<_> = discovery.projects.into_iter().map(|(path, _)| path).collect();
+            (paths, discovery.tombstones)
+        };
+        let exclude_dirs = vec!["node_modules".to_string()];
+
+        let walk = WalkOptions { exclude_dirs: exclude_dirs.clone(), ..Default::default() };
+        assert_eq!(found(&walk), (vec![dir.path().join("web")], vec![tombstone.clone()]));
+
+        let hidden = HiddenPolicy { include: true, ..Default::default() };
+        let walk = WalkOptions { exclude_dirs, hidden, ..Default::default() };
+        assert_eq!(
+            found(&walk),
+            (
+                vec![
+                    dir.path().join(".config/tool"),
+                    dir.path().join(".worktrees/feature"),
+                    dir.path().join("web"),
+                ],
+                vec![tombstone]
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_symlink_policy() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let shared = dir.path().join("shared/lib");
+        std::fs::create_dir_all(&shared).unwrap();
+        std::fs::write(shared.join("Cargo.toml"), "[package]").unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("app")).unwrap();
+        std::fs::write(root.join("app/Cargo.toml"), "[package]").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("shared"), root.join("linked")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("shared"), root.join("linked-again")).unwrap();
+        // 指向祖先目录的链接构成环
+        std::os::unix::fs::symlink(&root, root.join("app/loop")).unwrap();
+
+        let commands = vec![Cmd::new(CommandType::Cargo, vec!["Cargo.toml"])];
+        let found = |walk: &WalkOptions| {
+            let mut paths: Vec<_> = find_projects(&root, &commands, walk)
+                .into_iter()
+                .map(|(path, _)| path.canonicalize().unwrap())
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        let app = root.join("app").canonicalize().unwrap();
+        assert_eq!(found(&WalkOptions::default()), vec![app.clone()]);
+
+        let walk = WalkOptions { follow_symlinks: true, ..Default::default() };
+        assert_eq!(found(&walk), vec![app, shared.canonicalize().unwrap()]);
+    }
+
+    #[test]
+    fn test_discover_exclude_and_include_patterns() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for project in ["clients/legacy/app", "clients/modern/app", "libs/core", "libs/third_party/zlib", "tools"] {
+            std::fs::create_dir_all(dir.path().join(project)).unwrap();
+            std::fs::write(dir.path().join(project).join("package.json"), "{}").unwrap();
+        }
+        let commands = vec![Cmd::new(CommandType::NodeJs, vec!["package.json"])];
+        let found = |walk: WalkOptions| {
+            let mut paths: Vec<_> = find_projects(dir.path(), &commands, &walk)
+                .into_iter()
+                .map(|(path, _)| path.strip_prefix(dir.path()).unwrap().to_string_lossy().into_owned())
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        let walk = WalkOptions {
+            exclude: vec!["clients/legacy/*".to_string(), "**/third_party/**".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(found(walk), vec!["clients/modern/app", "libs/core", "tools"]);
+
+        let walk = WalkOptions { include: vec!["clients/*".to_string()], ..Default::default() };
+        assert_eq!(found(walk), vec!["clients/legacy/app", "clients/modern/app"]);
+
+        let walk = WalkOptions {
+            include: vec!["libs".to_string()],
+            exclude: vec!["libs/third_party".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(found(walk), vec!["libs/core"]);
+    }
+
+    #[test]
+    fn test_discover_cleans_workspace_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("package.json"), r#"{"workspaces":["packages/*"]}"#).unwrap();
+        for member in ["packages/a", "packages/b", "examples/demo"] {
+            std::fs::create_dir_all(root.join(member).join("node_modules")).unwrap();
+            std::fs::write(root.join(member).join("package.json"), "{}").unwrap();
+        }
+        std::fs::create_dir_all(root.join("node_modules")).unwrap();
+
+        let commands = vec![Cmd::new(CommandType::NodeJs, vec!["package.json"])];
+        let mut projects: Vec<PathBuf> =
+            find_projects(root, &commands, &WalkOptions::default()).into_iter().map(|(path, _)| path).collect();
+        projects.sort();
+        // examples/demo 不在工作区模式中，仍然单独清理
+        assert_eq!(projects, vec![root.to_path_buf(), root.join("examples/demo")]);
+        assert_eq!(
+            commands[0].artifact_paths(root, &IgnoreSet::default()),
+            vec![root.join("node_modules"), root.join("packages/a/node_modules"), root.join("packages/b/node_modules")]
+        );
+    }
+
+    #[test]
+    fn test_workspace_root_skips_excluded_and_kept_members() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("package.json"), r#"{"workspaces":["packages/*","clients/*/*"]}"#).unwrap();
+        for member in ["packages/a", "packages/old", "clients/legacy/pkg", "clients/kept/pkg"] {
+            std::fs::create_dir_all(root.join(member).join("node_modules")).unwrap();
+            std::fs::write(root.join(member).join("package.json"), "{}").unwrap();
+        }
+        std::fs::write(root.join(crate::constant::IGNORE_FILE), "packages/old/\n").unwrap();
+        std::fs::write(root.join("clients/kept").join(KEEP_MARKER), "").unwrap();
+
+        let commands = vec![Cmd::new(CommandType::NodeJs, vec!["package.json"])];
+        let walk = WalkOptions { exclude: vec!["clients/legacy/*".to_string()], ..Default::default() };
+        let discovery = discover(root, &commands, &walk);
+        let projects: Vec<_> = discovery.projects.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(projects, vec![root.to_path_buf()]);
+        assert_eq!(commands[0].artifact_paths(root, &discovery.ignores), vec![root.join("packages/a/node_modules")]);
+    }
+
+    #[test]
+    fn test_exclude_applies_to_artifacts_outside_the_project() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("app/.cargo")).unwrap();
+        std::fs::write(root.join("app/Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        std::fs::write(root.join("app/.cargo/config.toml"), "[build]\ntarget-dir = \"../cache/app/target\"\n").unwrap();
+        std::fs::create_dir_all(root.join("cache/app/target/debug")).unwrap();
+
+        let commands = vec![Cmd::new(CommandType::Cargo, vec!["Cargo.toml"])];
+        let walk = WalkOptions { exclude: vec!["cache/*".to_string()], ..Default::default() };
+        let discovery = discover(root, &commands, &walk);
+        assert_eq!(discovery.projects, vec![(root.join("app"), CommandType::Cargo)]);
+        // 输出目录不在遍历到的目录中，但位于被排除的 `cache/app` 之内
+        assert!(commands[0].artifact_paths(&root.join("app"), &discovery.ignores).is_empty());
+        assert_eq!(commands[0].artifact_paths(&root.join("app"), &IgnoreSet::default()), vec![root.join("cache/app/target")]);
+    }
+
+    #[test]
+    fn test_discover_shares_cargo_target_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n").unwrap();
+        for member in ["crates/core", "crates/cli"] {
+            std::fs::create_dir_all(root.join(member)).unwrap();
+            std::fs::write(root.join(member).join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+        }
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+
+        let commands = vec![Cmd::new(CommandType::Cargo, vec!["Cargo.toml"])];
+        let projects = find_projects(root, &commands, &WalkOptions::default());
+        assert_eq!(projects, vec![(root.to_path_buf(), CommandType::Cargo)]);
+        // 成员包的输出目录在工作区根目录
+        assert_eq!(commands[0].artifact_paths(&root.join("crates/cli"), &IgnoreSet::default()), vec![root.join("target")]);
+    }
+
+    #[tokio::test]
+    async fn test_ignored_target_is_kept_out_of_manifest() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let project = dir.path().join("app");
+        std::fs::create_dir_all(project.join("target/debug")).unwrap();
+        std::fs::write(project.join("Cargo.toml"), "[package]").unwrap();
+        std::fs::write(project.join(".rs_cleanignore"), "/target/\n").unwrap();
+        let history = History::new(dir.path().join("history"));
+
+        let commands = vec![Cmd::new(CommandType::Cargo, vec!["Cargo.toml"])];
+        let options = CleanOptions { history: Some(history.clone()), ..Default::default() };
+        let cleaned = do_clean_all(&[CleanRoot::new(&project, WalkOptions::default())], &commands, options).await;
+
+        assert_eq!(cleaned, 0);
+        assert!(project.join("target/debug").exists());
+        let runs = history.runs().unwrap();
+        assert_eq!(runs.len(), 1);
+        assert!(runs[0].projects.is_empty());
+        assert!(kept_line(&project, "target is excluded by .rs_cleanignore or --exclude").contains("Kept"));
+    }
+
+    #[test]
+    fn test_dedupe_roots() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let work = dir.path().join("work");
+        std::fs::create_dir_all(work.join("app")).unwrap();
+        std::fs::create_dir_all(dir.path().join("other")).unwrap();
+
+        let roots = [
+            work.join("app"),
+            dir.path().join("other"),
+            work.clone(),
+            work.join("../work"),
+        ]
+        .into_iter()
+        .map(|path| CleanRoot::new(path, WalkOptions::default()))
+        .collect();
+        let paths: Vec<_> = dedupe_roots(roots).into_iter().map(|root| root.path).collect();
+        assert_eq!(paths, vec![dir.path().join("other"), work]);
+    }
+}
@@ -1,56 +1,305 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 use colored::*;
 use rs_clean::cmd::Cmd;
 use rs_clean::config::Config;
 use rs_clean::constant::get_cmd_map;
-use rs_clean::do_clean_all;
+use rs_clean::delete::DeleteMode;
+use rs_clean::manifest::{undo_run, History};
+use rs_clean::quarantine::Quarantine;
+use rs_clean::utils::{format_timestamp, unix_now};
+use rs_clean::{do_clean_all, format_size, CleanOptions};
+use rs_clean::scan::{render_reports, scan_projects, sort_reports, ScanOptions};
 use rs_clean::utils::command_exists;
 use rs_clean::get_cpu_core_count;
-use std::time::Instant;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 /// A fast and simple tool to clean build artifacts from various projects.
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     #[clap(flatten)]
     config: Config,
 }
 
-#[tokio::main]
-async fn main() {
-    let cli = Cli::parse();
-    let config = cli.config;
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Report reclaimable space per project without cleaning anything
+    Scan {
+        #[clap(flatten)]
+        config: Box<Config>,
 
-    // Normal cleaning operation
-    let start = Instant::now();
+        #[clap(flatten)]
+        options: ScanOptions,
+    },
+    /// Move a project's quarantined artifacts back into place
+    Restore {
+        /// Project directory whose artifacts should be restored
+        #[clap(default_value = ".")]
+        project: PathBuf,
 
-    // Validate configuration
+        /// Quarantine directory (defaults to ~/.rs_clean/quarantine)
+        #[clap(long, value_name = "DIR")]
+        quarantine: Option<PathBuf>,
+    },
+    /// Permanently delete quarantined artifacts past their retention period
+    Purge {
+        /// Quarantine directory (defaults to ~/.rs_clean/quarantine)
+        #[clap(long, value_name = "DIR")]
+        quarantine: Option<PathBuf>,
+
+        /// Delete every quarantined artifact, including those still within retention
+        #[clap(long, action)]
+        all: bool,
+    },
+    /// Restore what the last run moved to trash or quarantine
+    Undo {
+        /// Run to undo (defaults to the most recent run that has not been undone)
+        #[clap(long)]
+        run: Option<String>,
+
+        /// History directory (defaults to ~/.rs_clean/history)
+        #[clap(long, value_name = "DIR")]
+        history_dir: Option<PathBuf>,
+    },
+    /// List past runs
+    History {
+        /// History directory (defaults to ~/.rs_clean/history)
+        #[clap(long, value_name = "DIR")]
+        history_dir: Option<PathBuf>,
+    },
+}
+
+fn exit_with_error(message: impl std::fmt::Display) -> ! {
+    eprintln!("{} {}", "Error:".red(), message);
+    std::process::exit(1);
+}
+
+/// Open the quarantine; the retention is taken from each entry as written, so it isn't needed here
+fn open_quarantine(root: Option<PathBuf>) -> Quarantine {
+    match root.or_else(Quarantine::default_root) {
+        Some(root) => Quarantine::new(root, Duration::ZERO),
+        None => exit_with_error("Cannot determine the home directory; pass --quarantine"),
+    }
+}
+
+fn run_restore(project: PathBuf, quarantine: Option<PathBuf>) {
+    let quarantine = open_quarantine(quarantine);
+    let report = quarantine
+        .restore_project(&project)
+        .unwrap_or_else(|e| exit_with_error(format!("Failed to restore {}: {}", project.display(), e)));
+
+    for path in &report.restored {
+        println!("✓ {} {}", "Restored".green(), path.display());
+    }
+    for path in &report.skipped {
+        println!("{} {} already exists, left in quarantine", "Skipped:".yellow(), path.display());
+    }
+    if report.restored.is_empty() && report.skipped.is_empty() {
+        println!("{}", "No quarantined artifacts found for this project".yellow());
+    }
+}
+
+fn open_history(dir: Option<PathBuf>) -> History {
+    match dir.or_else(History::default_dir) {
+        Some(dir) => History::new(dir),
+        None => exit_with_error("Cannot determine the home directory; pass --history-dir"),
+    }
+}
+
+fn run_undo(run: Option<String>, history_dir: Option<PathBuf>) {
+    let history = open_history(history_dir);
+    let mut manifest = match history.find(run.as_deref()) {
+        Ok(Some(manifest)) => manifest,
+        Ok(None) => exit_with_error("No run to undo"),
+        Err(e) => exit_with_error(format!("Failed to read {}: {}", history.dir().display(), e)),
+    };
+    if manifest.undone_at.is_some() {
+        exit_with_error(format!("Run {} has already been undone", manifest.id));
+    }
+
+    println!("Undoing run {} ({})", manifest.id.cyan(), format_timestamp(manifest.started_at));
+    let report = undo_run(&manifest);
+    for path in &report.restored {
+        println!("✓ {} {}", "Restored".green(), path.display());
+    }
+    for (path, reason) in &report.unrecoverable {
+        println!("✗ {} {} - {}", "Cannot restore".red(), path.display(), reason);
+    }
+    println!(
+        "Restored {} artifact(s), {} cannot be restored",
+        report.restored.len(),
+        report.unrecoverable.len()
+    );
+
+    manifest.undone_at = Some(unix_now());
+    if let Err(e) = history.save(&manifest) {
+        exit_with_error(format!("Failed to update run manifest: {}", e));
+    }
+}
+
+fn run_history(history_dir: Option<PathBuf>) {
+    let history = open_history(history_dir);
+    let runs = history
+        .runs()
+        .unwrap_or_else(|e| exit_with_error(format!("Failed to read {}: {}", history.dir().display(), e)));
+    if runs.is_empty() {
+        println!("{}", "No runs recorded".yellow());
+        return;
+    }
+
+    println!("{:<24} {:<20} {:>8} {:>12}  ROOTS", "RUN", "STARTED (UTC)", "PROJECTS", "FREED");
+    for run in runs.iter().rev() {
+        println!(
+            "{:<24} {:<20} {:>8} {:>12}  {}{}",
+            run.id,
+            format_timestamp(run.started_at),
+            run.projects.len(),
+            format_size(run.freed_bytes()),
+            run.roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>().join(", "),
+            if run.undone_at.is_some() { " (undone)" } else { "" }
+        );
+    }
+}
+
+fn run_purge(quarantine: Option<PathBuf>, all: bool) {
+    let quarantine = open_quarantine(quarantine);
+    let purged = quarantine
+        .purge(all, get_cpu_core_count())
+        .unwrap_or_else(|e| exit_with_error(format!("Failed to purge {}: {}", quarantine.root().display(), e)));
+
+    for entry in &purged {
+        println!("✓ {} {}", "Purged".green(), entry.original_path.display());
+    }
+    println!("Purged {} quarantined artifact(s)", purged.len());
+}
+
+/// Argument names given explicitly on the command line (matching the field names of `Config`)
+fn explicit_args(matches: &ArgMatches) -> Vec<String> {
+    matches
+        .ids()
+        .filter(|id| matches.value_source(id.as_str()) == Some(ValueSource::CommandLine))
+        .map(|id| id.to_string())
+        .collect()
+}
+
+/// Merge the config file given with `--config`
+fn load_config(config: Config, matches: &ArgMatches) -> Config {
+    config
+        .merge_config_file(&explicit_args(matches))
+        .unwrap_or_else(|e| exit_with_error(e))
+}
+
+fn validate_config(config: &Config) {
     if let Err(e) = config.validate() {
         eprintln!("{} Configuration validation failed:", "Error:".red());
         eprintln!("  {}", e);
         eprintln!("{} Please check your configuration.", "Hint:".yellow());
         std::process::exit(1);
     }
+}
+
+/// Build the command list; with `require_tools`, keep only cleanup commands available on this machine
+fn build_cmd_list(config: &Config, require_tools: bool) -> Vec<Cmd> {
+    let mut cmd_list = vec![];
+    for (cmd_type, value) in get_cmd_map() {
+        if (!require_tools || command_exists(cmd_type.as_str()))
+            && !config.exclude_dir.contains(&cmd_type.as_str().to_string())
+        {
+            cmd_list.push(
+                Cmd::new(*cmd_type, value.clone())
+                    .exclude_dirs(config.exclude_dir.clone())
+                    .python(config.python_options())
+                    .node(config.node_options())
+                    .go(config.go_options())
+                    .cargo(config.cargo_options()),
+            );
+        }
+    }
+    cmd_list
+}
+
+async fn run_scan(config: Config, options: ScanOptions) {
+    validate_config(&config);
+
+    // 扫描是只读操作，不需要本机安装对应的构建工具
+    let cmd_list = build_cmd_list(&config, false);
+    let mut reports = scan_projects(
+        &config.clean_roots(),
+        &cmd_list,
+        Some(get_cpu_core_count()),
+        config.project_budget(),
+        config.artifact_budget(),
+    )
+    .await;
+
+    sort_reports(&mut reports, options.sort, options.top);
+    print!("{}", render_reports(&reports, &options));
+}
+
+#[tokio::main]
+async fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    match cli.command {
+        Some(Commands::Scan { config, options }) => {
+            let scan_matches = matches.subcommand_matches("scan").unwrap_or(&matches);
+            return run_scan(load_config(*config, scan_matches), options).await;
+        }
+        Some(Commands::Restore { project, quarantine }) => return run_restore(project, quarantine),
+        Some(Commands::Purge { quarantine, all }) => return run_purge(quarantine, all),
+        Some(Commands::Undo { run, history_dir }) => return run_undo(run, history_dir),
+        Some(Commands::History { history_dir }) => return run_history(history_dir),
+        None => {}
+    }
+    let config = load_config(cli.config, &matches);
+
+    // Normal cleaning operation
+    let start = Instant::now();
+
+    // Validate configuration
+    validate_config(&config);
 
     if config.verbose {
         println!("{} Using configuration:", "Info:".blue());
-        println!("  Path: {}", config.path.display());
+        for root in config.clean_roots() {
+            println!("  Root: {}", root.path.display());
+        }
         if !config.exclude_dir.is_empty() {
             println!("  Exclude dirs: {}", config.exclude_dir.join(", "));
         }
+        if !config.exclude.is_empty() {
+            println!("  Exclude patterns: {}", config.exclude.join(", "));
+        }
+        if !config.include.is_empty() {
+            println!("  Include patterns: {}", config.include.join(", "));
+        }
         println!("  Max directory depth: {}", config.max_directory_depth);
         println!("  Max files per project: {}", config.max_files_per_project);
+        let limit_str = |limit: Option<usize>| limit.map_or("unlimited".to_string(), |l| l.to_string());
+        println!("  Max artifact depth: {}", limit_str(config.max_artifact_depth));
+        println!("  Max artifact files: {}", limit_str(config.max_artifact_files));
         println!();
     }
 
-    let map = get_cmd_map();
-    let mut cmd_list = vec![];
-    for (cmd_type, value) in map {
-        if command_exists(cmd_type.as_str()) && !config.exclude_dir.contains(&cmd_type.as_str().to_string()) {
-            cmd_list.push(Cmd::new(*cmd_type, value.clone()));
-        }
+    let delete_mode = config.delete_mode().unwrap_or_else(|e| exit_with_error(e));
+    match &delete_mode {
+        DeleteMode::Permanent => {}
+        DeleteMode::Trash(dir) => println!("Moving artifacts to trash: {}", dir.display()),
+        DeleteMode::Quarantine(quarantine) => println!(
+            "Moving artifacts to quarantine: {} (kept for {} day{})",
+            quarantine.root().display(),
+            config.retention_days,
+            if config.retention_days == 1 { "" } else { "s" }
+        ),
     }
 
+    let cmd_list = build_cmd_list(&config, true);
+
     let init_cmd: Vec<String> = cmd_list.iter().map(|cmd| cmd.command_type.as_str().to_string()).collect();
     println!(
         "Found supported clean commands: {}",
@@ -66,18 +315,23 @@ async fn main() {
         cpu_cores
     );
     println!(
-        "Safety limits: max depth {}, max files {}",
+        "Project size budget: max depth {}, max files {}",
         config.max_directory_depth,
         config.max_files_per_project
     );
 
     let count = do_clean_all(
-        &config.path,
+        &config.clean_roots(),
         &cmd_list,
-        &config.exclude_dir,
-        Some(cpu_cores),
-        config.max_directory_depth,
-        config.max_files_per_project,
+        CleanOptions {
+            max_concurrent: Some(cpu_cores),
+            project_budget: config.project_budget(),
+            artifact_budget: config.artifact_budget(),
+            verify_size: !config.no_verify_size,
+            delete_mode,
+            fix_permissions: config.fix_permissions,
+            history: config.history(),
+        },
     )
     .await;
     let elapsed = start.elapsed();
@@ -0,0 +1,255 @@
+use crate::delete::Disposal;
+use crate::quarantine::Quarantine;
+use crate::trash::restore_from_trash;
+use crate::utils::unix_now;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A single removed build artifact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactRecord {
+    #[serde(with = "crate::os_path")]
+    pub path: PathBuf,
+    pub disk_bytes: u64,
+    pub apparent_bytes: u64,
+    pub disposal: Disposal,
+}
+
+/// Cleanup record of one project
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectRecord {
+    #[serde(with = "crate::os_path")]
+    pub path: PathBuf,
+    pub ecosystem: String,
+    /// Command that cleaned the project when an external command did, e.g. `cargo clean`
+    pub external_command: Option<String>,
+    pub artifacts: Vec<ArtifactRecord>,
+    /// Disk space freed; empty when an external command cleaned and the size wasn't re-measured
+    pub freed_bytes: Option<u64>,
+    pub apparent_bytes: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Cleanup record of a global cache (e.g. `go clean -modcache`) that belongs to no project
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheRecord {
+    pub command: String,
+    /// Cache directories used to measure the freed space
+    #[serde(with = "crate::os_path::many")]
+    pub paths: Vec<PathBuf>,
+    pub freed_bytes: u64,
+    pub apparent_bytes: u64,
+}
+
+/// Manifest of one run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub id: String,
+    /// Roots cleaned in this run; older manifests recorded a single `root`
+    #[serde(default, alias = "root", with = "crate::os_path::many")]
+    pub roots: Vec<PathBuf>,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub projects: Vec<ProjectRecord>,
+    /// Global caches cleaned, counted separately from [`RunManifest::freed_bytes`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub global_caches: Vec<CacheRecord>,
+    /// When `undo` was run
+    pub undone_at: Option<u64>,
+}
+
+impl RunManifest {
+    pub fn new(roots: &[PathBuf]) -> Self {
+        let started_at = unix_now();
+        Self {
+            id: format!("{}-{}", started_at, std::process::id()),
+            roots: roots.to_vec(),
+            started_at,
+            ..Default::default()
+        }
+    }
+
+    /// Disk space this run freed from projects
+    pub fn freed_bytes(&self) -> u64 {
+        self.projects.iter().filter_map(|p| p.freed_bytes).sum()
+    }
+}
+
+/// Result of an undo
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    pub restored: Vec<PathBuf>,
+    /// Paths that could not be restored, with the reason
+    pub unrecoverable: Vec<(PathBuf, String)>,
+}
+
+/// Restore the manifest's build artifacts from trash or quarantine and list what could not be restored
+pub fn undo_run(manifest: &RunManifest) -> UndoReport {
+    let mut report = UndoReport::default();
+    for project in &manifest.projects {
+        if let Some(command) = &project.external_command {
+            report
+                .unrecoverable
+                .push((project.path.clone(), format!("cleaned by `{}`", command)));
+        }
+        for artifact in &project.artifacts {
+            let result = match &artifact.disposal {
+                Disposal::Deleted => Err("permanently deleted".to_string()),
+                Disposal::Trashed { location } => restore_from_trash(location, &artifact.path)
+                    .map_err(|e| format!("restore from trash failed: {}", e)),
+                Disposal::Quarantined { entry } => Quarantine::read_entry(entry)
+                    .and_then(|record| Quarantine::restore_entry(entry, &record))
+                    .map_err(|e| format!("restore from quarantine failed: {}", e)),
+            };
+            match result {
+                Ok(true) => report.restored.push(artifact.path.clone()),
+                Ok(false) => report
+                    .unrecoverable
+                    .push((artifact.path.clone(), "path already exists".to_string())),
+                Err(reason) => report.unrecoverable.push((artifact.path.clone(), reason)),
+            }
+        }
+    }
+    for cache in &manifest.global_caches {
+        for path in &cache.paths {
+            report
+                .unrecoverable
+                .push((path.clone(), format!("cleaned by `{}`", cache.command)));
+        }
+    }
+    report
+}
+
+/// Run history, one JSON file per run
+#[derive(Debug, Clone)]
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Default history directory `~/.rs_clean/history`
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".rs_clean").join("history"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write (or overwrite) the manifest of a run
+    pub fn save(&self, manifest: &RunManifest) -> io::Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("{}.json", manifest.id));
+        std::fs::write(&path, serde_json::to_vec_pretty(manifest)?)?;
+        Ok(path)
+    }
+
+    /// All recorded runs, sorted by start time
+    pub fn runs(&self) -> io::Result<Vec<RunManifest>> {
+        let mut runs = vec![];
+        let dir = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(runs),
+            Err(e) => return Err(e),
+        };
+        for item in dir.flatten() {
+            let Ok(content) = std::fs::read(item.path()) else {
+                continue;
+            };
+            if let Ok(manifest) = serde_json::from_slice::<RunManifest>(&content) {
+                runs.push(manifest);
+            }
+        }
+        runs.sort_by(|a, b| (a.started_at, &a.id).cmp(&(b.started_at, &b.id)));
+        Ok(runs)
+    }
+
+    /// The run with the given id, or the latest run that hasn't been undone
+    pub fn find(&self, id: Option<&str>) -> io::Result<Option<RunManifest>> {
+        let runs = self.runs()?;
+        Ok(match id {
+            Some(id) => runs.into_iter().find(|run| run.id == id),
+            None => runs.into_iter().rev().find(|run| run.undone_at.is_none()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn record(path: PathBuf, disposal: Disposal) -> ArtifactRecord {
+        ArtifactRecord { path, disk_bytes: 4096, apparent_bytes: 10, disposal }
+    }
+
+    #[test]
+    fn test_history_finds_latest_undoable_run() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let history = History::new(dir.path().to_path_buf());
+        for (id, started_at, undone_at) in [("a", 1, None), ("b", 2, Some(3)), ("c", 0, None)] {
+            let run = RunManifest { id: id.to_string(), started_at, undone_at, ..Default::default() };
+            history.save(&run).unwrap();
+        }
+
+        let ids: Vec<_> = history.runs().unwrap().into_iter().map(|run| run.id).collect();
+        assert_eq!(ids, vec!["c", "a", "b"]);
+        assert_eq!(history.find(None).unwrap().unwrap().id, "a");
+        assert_eq!(history.find(Some("b")).unwrap().unwrap().id, "b");
+        assert!(History::new(dir.path().join("missing")).runs().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_manifest_reads_single_root() {
+        let run: RunManifest =
+            serde_json::from_str(r#"{"id":"a","root":"/work","started_at":1,"finished_at":2,"projects":[]}"#).unwrap();
+        assert_eq!(run.roots, vec![PathBuf::from("/work")]);
+    }
+
+    #[test]
+    fn test_undo_restores_quarantined_and_lists_the_rest() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("ml/.venv")).unwrap();
+        let project = dir.path().join("ml").canonicalize().unwrap();
+        let quarantine = Quarantine::new(dir.path().join("q"), Duration::from_secs(60));
+        let entry = quarantine.store(&project.join(".venv"), &project).unwrap();
+
+        let manifest = RunManifest {
+            projects: vec![
+                ProjectRecord {
+                    path: project.clone(),
+                    artifacts: vec![
+                        record(project.join(".venv"), Disposal::Quarantined { entry }),
+                        record(project.join("build"), Disposal::Deleted),
+                    ],
+                    ..Default::default()
+                },
+                ProjectRecord {
+                    path: dir.path().join("crate"),
+                    external_command: Some("cargo clean".to_string()),
+                    ..Default::default()
+                },
+            ],
+            global_caches: vec![CacheRecord {
+                command: "go clean -modcache".to_string(),
+                paths: vec![PathBuf::from("/home/me/go/pkg/mod")],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let report = undo_run(&manifest);
+        assert_eq!(report.restored, vec![project.join(".venv")]);
+        assert!(project.join(".venv").exists());
+        let unrecoverable: Vec<_> = report.unrecoverable.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            unrecoverable,
+            vec![project.join("build"), dir.path().join("crate"), PathBuf::from("/home/me/go/pkg/mod")]
+        );
+    }
+}
@@ -0,0 +1,477 @@
+use crate::constant::{NodeTool, KEEP_MARKER, NODE_GENERIC_OUTPUTS, NODE_TOOLS, NODE_WORKSPACE_CACHES, YARN_PNP_ARTIFACTS};
+use crate::ignore::{IgnoreSet, MATCH_OPTIONS};
+use crate::os_path;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use walkdir::WalkDir;
+
+/// Cleaning options for Node.js projects (`[node]` in the config file)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeOptions {
+    /// After cleaning, run the cache cleanup command once for every package manager that was used
+    pub prune: bool,
+}
+
+/// Cleanup-related fields of `package.json`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PackageJson {
+    pub dependencies: BTreeMap<String, serde_json::Value>,
+    pub dev_dependencies: BTreeMap<String, serde_json::Value>,
+    pub scripts: BTreeMap<String, String>,
+    /// The `packageManager` field, e.g. `pnpm@9.1.0`
+    pub package_manager: Option<String>,
+    pub workspaces: Option<Workspaces>,
+}
+
+/// The `workspaces` field: a list of patterns, or Yarn 1's `{ "packages": [...] }` form
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Workspaces {
+    Patterns(Vec<String>),
+    Config {
+        #[serde(default)]
+        packages: Vec<String>,
+    },
+}
+
+impl PackageJson {
+    /// Read `dir/package.json`; `None` when it is missing or can't be parsed
+    pub fn load(dir: &Path) -> Option<Self> {
+        let content = std::fs::read(dir.join("package.json")).ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    fn depends_on(&self, package: &str) -> bool {
+        let mut names = self.dependencies.keys().chain(self.dev_dependencies.keys());
+        match package.strip_suffix('*') {
+            // `@scope/*` 匹配整个作用域
+            Some(scope) => names.any(|name| name.starts_with(scope)),
+            None => names.any(|name| name == package),
+        }
+    }
+
+    /// Whether the scripts invoke `command` (compared word by word after splitting on shell separators)
+    fn runs(&self, command: &str) -> bool {
+        self.scripts.values().any(|script| {
+            script
+                .split(|c: char| c.is_whitespace() || matches!(c, '&' | '|' | ';' | '(' | ')'))
+                .any(|word| word == command)
+        })
+    }
+
+    /// Known tools used by the project
+    pub fn tools(&self) -> Vec<&'static NodeTool> {
+        NODE_TOOLS
+            .iter()
+            .filter(|tool| {
+                tool.packages.iter().any(|package| self.depends_on(package))
+                    || tool.commands.iter().any(|command| self.runs(command))
+            })
+            .collect()
+    }
+}
+
+/// Package manager used by a project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PackageManager {
+    Npm,
+    Pnpm,
+    /// Yarn 1.x
+    Yarn,
+    /// Yarn 2 and later
+    YarnBerry,
+    Bun,
+}
+
+impl PackageManager {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::YarnBerry => "yarn berry",
+            PackageManager::Bun => "bun",
+        }
+    }
+
+    /// Detect the package manager of the project in `dir`: the `packageManager` field first, then lock files
+    pub fn detect(dir: &Path, package: &PackageJson) -> Option<Self> {
+        if let Some(manager) = package.package_manager.as_deref().and_then(Self::parse) {
+            return Some(manager);
+        }
+        let has = |name: &str| dir.join(name).exists();
+        if has("pnpm-lock.yaml") {
+            Some(PackageManager::Pnpm)
+        } else if has("bun.lockb") || has("bun.lock") {
+            Some(PackageManager::Bun)
+        } else if has("yarn.lock") {
+            // Yarn Berry 的项目总会有 `.yarnrc.yml`；Yarn 1 使用 `.yarnrc`
+            if has(".yarnrc.yml") || has(".pnp.cjs") || has(".yarn/releases") {
+                Some(PackageManager::YarnBerry)
+            } else {
+                Some(PackageManager::Yarn)
+            }
+        } else if has("package-lock.json") || has("npm-shrinkwrap.json") {
+            Some(PackageManager::Npm)
+        } else {
+            None
+        }
+    }
+
+    /// Parse the `packageManager` field (`<name>@<version>[+<hash>]`)
+    fn parse(field: &str) -> Option<Self> {
+        let (name, version) = field.split_once('@')?;
+        match name {
+            "npm" => Some(PackageManager::Npm),
+            "pnpm" => Some(PackageManager::Pnpm),
+            "bun" => Some(PackageManager::Bun),
+            "yarn" => {
+                let major: u32 = version.split('.').next()?.parse().ok()?;
+                Some(if major >= 2 { PackageManager::YarnBerry } else { PackageManager::Yarn })
+            }
+            _ => None,
+        }
+    }
+
+    /// Command that cleans the package manager's shared cache; none for npm, whose cache can only be deleted as a whole
+    ///
+    /// Yarn Berry cleans only the global mirror (`--mirror`), leaving the `.yarn/cache` that zero-install projects check in.
+    pub fn prune_command(&self) -> Option<&'static [&'static str]> {
+        match self {
+            PackageManager::Npm => None,
+            PackageManager::Pnpm => Some(&["pnpm", "store", "prune"]),
+            PackageManager::Yarn => Some(&["yarn", "cache", "clean"]),
+            PackageManager::YarnBerry => Some(&["yarn", "cache", "clean", "--mirror"]),
+            PackageManager::Bun => Some(&["bun", "pm", "cache", "rm"]),
+        }
+    }
+
+    /// Run the cache cleanup command in `dir`, returning the command line that ran
+    pub async fn prune(&self, dir: &Path) -> io::Result<String> {
+        let Some((program, args)) = self.prune_command().and_then(|command| command.split_first()) else {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} has no prune command", self.as_str())));
+        };
+        let command_line = std::iter::once(program).chain(args).copied().collect::<Vec<_>>().join(" ");
+        let output = Command::new(program).args(args).current_dir(dir).output().await?;
+        if output.status.success() {
+            Ok(command_line)
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("no output");
+            Err(io::Error::other(format!("`{}` failed ({}): {}", command_line, output.status, reason.trim())))
+        }
+    }
+}
+
+/// Local files a package manager creates outside `node_modules`
+///
+/// Currently only Yarn Berry: Plug'n'Play's `.pnp.cjs`, `.yarn/unplugged` and so on, plus `.yarn/cache`.
+/// Zero-install projects check `.yarn/cache` in, so it is kept when it is inside a git repository and not ignored.
+pub fn package_manager_artifacts(dir: &Path) -> Vec<PathBuf> {
+    let package = PackageJson::load(dir).unwrap_or_default();
+    if PackageManager::detect(dir, &package) != Some(PackageManager::YarnBerry) {
+        return vec![];
+    }
+
+    let mut paths = vec![];
+    let cache = dir.join(".yarn/cache");
+    if cache.is_dir() {
+        let in_repo = dir.ancestors().any(|ancestor| ancestor.join(".git").exists());
+        if !in_repo || IgnoreSet::gitignores(dir).is_ignored(&cache, true) {
+            paths.push(cache);
+        }
+    }
+    paths.extend(
+        YARN_PNP_ARTIFACTS
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|path| std::fs::symlink_metadata(path).is_ok()),
+    );
+    paths
+}
+
+/// A Node.js workspace (monorepo): the root and its member packages
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub members: Vec<PathBuf>,
+}
+
+impl Workspace {
+    /// Returns the workspace when `dir` is a workspace root
+    ///
+    /// Members come from the patterns in the `workspaces` field, `pnpm-workspace.yaml` and `lerna.json`;
+    /// with only `nx.json` or `turbo.json` and no patterns, every package under the root is a member.
+    /// The member search skips `node_modules`, hidden directories, `exclude_dirs` and directories containing `.rs_clean_keep`.
+    pub fn detect(dir: &Path, exclude_dirs: &[String]) -> Option<Self> {
+        let patterns = workspace_patterns(dir)?;
+        let (include, exclude): (Vec<&str>, Vec<&str>) = patterns
+            .iter()
+            .map(|pattern| pattern.trim_start_matches("./").trim_end_matches('/'))
+            .partition(|pattern| !pattern.starts_with('!'));
+        let compile = |patterns: Vec<&str>| -> Vec<glob::Pattern> {
+            patterns
+                .into_iter()
+                .filter_map(|pattern| glob::Pattern::new(pattern.trim_start_matches('!')).ok())
+                .collect()
+        };
+        let (include, exclude) = (compile(include), compile(exclude));
+
+        let mut members = vec![];
+        let mut walker = WalkDir::new(dir).min_depth(1).into_iter();
+        while let Some(entry) = walker.next() {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let (name, path) = (entry.file_name(), entry.path());
+            if name == "node_modules"
+                || name.as_encoded_bytes().starts_with(b".")
+                || exclude_dirs.iter().any(|excluded| OsStr::new(excluded) == name)
+                || path.join(KEEP_MARKER).exists()
+            {
+                walker.skip_current_dir();
+                continue;
+            }
+            if !path.join("package.json").is_file() {
+                continue;
+            }
+            let relative = path.strip_prefix(dir).unwrap_or(path);
+            let relative = os_path::glob_text(relative.as_os_str()).replace('\\', "/");
+            let matches = |patterns: &[glob::Pattern]| patterns.iter().any(|p| p.matches_with(&relative, MATCH_OPTIONS));
+            if (include.is_empty() || matches(&include)) && !matches(&exclude) {
+                members.push(path.to_path_buf());
+            }
+        }
+        members.sort();
+        Some(Self { root: dir.to_path_buf(), members })
+    }
+
+    /// Workspace tool caches: `.nx/cache`, `node_modules/.cache/turbo` and Turborepo's `.turbo` in each member package
+    pub fn tool_caches(&self) -> Vec<PathBuf> {
+        let mut caches = vec![];
+        for (marker, paths) in NODE_WORKSPACE_CACHES {
+            if self.root.join(marker).is_file() {
+                caches.extend(paths.iter().map(|path| self.root.join(path)));
+            }
+        }
+        if self.root.join("turbo.json").is_file() {
+            caches.extend(self.members.iter().map(|member| member.join(".turbo")));
+        }
+        caches.retain(|path| path.is_dir());
+        caches
+    }
+}
+
+/// Member patterns declared in a workspace root; `None` when it isn't one
+fn workspace_patterns(dir: &Path) -> Option<Vec<String>> {
+    let mut patterns = vec![];
+    let mut is_root = false;
+    if let Some(workspaces) = PackageJson::load(dir).and_then(|package| package.workspaces) {
+        is_root = true;
+        patterns.extend(match workspaces {
+            Workspaces::Patterns(patterns) | Workspaces::Config { packages: patterns } => patterns,
+        });
+    }
+    if let Ok(content) = std::fs::read_to_string(dir.join("pnpm-workspace.yaml")) {
+        is_root = true;
+        patterns.extend(pnpm_workspace_packages(&content));
+    }
+    if let Ok(content) = std::fs::read(dir.join("lerna.json")) {
+        #[derive(Deserialize)]
+        struct Lerna {
+            #[serde(default = "default_lerna_packages")]
+            packages: Vec<String>,
+        }
+        fn default_lerna_packages() -> Vec<String> {
+            vec!["packages/*".to_string()]
+        }
+        if let Ok(lerna) = serde_json::from_slice::<Lerna>(&content) {
+            is_root = true;
+            patterns.extend(lerna.packages);
+        }
+    }
+    if NODE_WORKSPACE_CACHES.iter().any(|(marker, _)| dir.join(marker).is_file()) {
+        is_root = true;
+    }
+    is_root.then_some(patterns)
+}
+
+/// Read the `packages` list from `pnpm-workspace.yaml`
+///
+/// Only the forms the file actually uses are supported: a block list (`- 'packages/*'`) or a one-line `[a, b]`.
+fn pnpm_workspace_packages(content: &str) -> Vec<String> {
+    let unquote = |item: &str| {
+        let item = item.split(" #").next().unwrap_or(item).trim();
+        item.trim_matches(|c| c == '\'' || c == '"').to_string()
+    };
+    let mut packages = vec![];
+    let mut in_packages = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) && !trimmed.starts_with('-') {
+            in_packages = false;
+            if let Some(value) = trimmed.strip_prefix("packages:") {
+                in_packages = true;
+                if let Some(items) = value.trim().strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                    packages.extend(items.split(',').map(unquote).filter(|item| !item.is_empty()));
+                }
+            }
+        } else if in_packages {
+            if let Some(item) = trimmed.strip_prefix('-') {
+                packages.push(unquote(item));
+            }
+        }
+    }
+    packages
+}
+
+/// Framework output directories in a project that can be removed
+///
+/// A directory is removed only when a detected tool generates it or `.gitignore` ignores it;
+/// directories of the same name checked into the repo, such as `build/`, are kept.
+pub fn framework_outputs(dir: &Path) -> Vec<PathBuf> {
+    let package = PackageJson::load(dir).unwrap_or_default();
+    let mut produced: Vec<&str> = package.tools().iter().flat_map(|tool| tool.outputs.iter().copied()).collect();
+    produced.sort();
+    produced.dedup();
+
+    let mut candidates: Vec<&str> = NODE_TOOLS
+        .iter()
+        .flat_map(|tool| tool.outputs.iter().copied())
+        .chain(NODE_GENERIC_OUTPUTS.iter().copied())
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    let mut gitignores: Option<IgnoreSet> = None;
+    let mut outputs = vec![];
+    for name in candidates {
+        let path = dir.join(name);
+        if !path.is_dir() {
+            continue;
+        }
+        let keep = !produced.contains(&name)
+            && !gitignores.get_or_insert_with(|| IgnoreSet::gitignores(dir)).is_ignored(&path, true);
+        if !keep {
+            outputs.push(path);
+        }
+    }
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_tools_from_dependencies_and_scripts() {
+        let package: PackageJson = serde_json::from_str(
+            r#"{
+                "dependencies": { "next": "14.0.0" },
+                "devDependencies": { "@storybook/react": "^8" },
+                "scripts": { "test": "NODE_ENV=test jest --coverage", "lint": "eslint ." }
+            }"#,
+        )
+        .unwrap();
+        let mut outputs: Vec<_> = package.tools().iter().flat_map(|tool| tool.outputs.iter().copied()).collect();
+        outputs.sort();
+        assert_eq!(outputs, vec![".next", "coverage", "out", "storybook-static"]);
+    }
+
+    #[test]
+    fn test_framework_outputs_keep_checked_in_build() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        std::fs::write(root.join(".gitignore"), "/web/dist\n").unwrap();
+        let web = root.join("web");
+        for name in [".next", "out", "build", "dist", ".turbo", "coverage"] {
+            std::fs::create_dir_all(web.join(name)).unwrap();
+        }
+        std::fs::write(web.join("package.json"), r#"{"dependencies":{"next":"14"}}"#).unwrap();
+
+        let outputs = framework_outputs(&web);
+        // `build/`、`.turbo/` 和 `coverage/` 既不是已识别工具的输出，也没有被忽略
+        assert_eq!(outputs, vec![web.join(".next"), web.join("dist"), web.join("out")]);
+    }
+
+    #[test]
+    fn test_workspace_members() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        for member in ["packages/ui", "packages/legacy", "apps/web", "apps/web/node_modules/dep", "tools/script"] {
+            std::fs::create_dir_all(root.join(member)).unwrap();
+            std::fs::write(root.join(member).join("package.json"), "{}").unwrap();
+        }
+        assert_eq!(Workspace::detect(root, &[]), None);
+
+        std::fs::write(
+            root.join("pnpm-workspace.yaml"),
+            "packages:\n  - 'packages/*'\n  - \"apps/**\"  # apps\n  - '!packages/legacy'\ncatalog:\n  react: ^18\n",
+        )
+        .unwrap();
+        let workspace = Workspace::detect(root, &[]).unwrap();
+        assert_eq!(workspace.members, vec![root.join("apps/web"), root.join("packages/ui")]);
+
+        // 只有 turbo.json 时，所有包都是成员
+        std::fs::remove_file(root.join("pnpm-workspace.yaml")).unwrap();
+        std::fs::write(root.join("turbo.json"), "{}").unwrap();
+        std::fs::create_dir_all(root.join("node_modules/.cache/turbo")).unwrap();
+        std::fs::create_dir_all(root.join("apps/web/.turbo")).unwrap();
+        let workspace = Workspace::detect(root, &[]).unwrap();
+        assert_eq!(workspace.members.len(), 4);
+        assert_eq!(
+            workspace.tool_caches(),
+            vec![root.join("node_modules/.cache/turbo"), root.join("apps/web/.turbo")]
+        );
+    }
+
+    #[test]
+    fn test_detect_package_manager() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        let detect = |package: &str| PackageManager::detect(root, &serde_json::from_str(package).unwrap());
+
+        assert_eq!(detect("{}"), None);
+        std::fs::write(root.join("yarn.lock"), "").unwrap();
+        assert_eq!(detect("{}"), Some(PackageManager::Yarn));
+        std::fs::write(root.join(".yarnrc.yml"), "nodeLinker: pnp\n").unwrap();
+        assert_eq!(detect("{}"), Some(PackageManager::YarnBerry));
+        // `packageManager` 字段优先于锁文件
+        assert_eq!(detect(r#"{"packageManager":"pnpm@9.1.0+sha256.abc"}"#), Some(PackageManager::Pnpm));
+        assert_eq!(detect(r#"{"packageManager":"yarn@1.22.19"}"#), Some(PackageManager::Yarn));
+        assert_eq!(detect(r#"{"packageManager":"yarn@4.1.0"}"#), Some(PackageManager::YarnBerry));
+    }
+
+    #[test]
+    fn test_yarn_berry_artifacts_keep_zero_install_cache() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(root.join("package.json"), r#"{"packageManager":"yarn@4.1.0"}"#).unwrap();
+        std::fs::create_dir_all(root.join(".yarn/cache")).unwrap();
+        std::fs::create_dir_all(root.join(".yarn/unplugged")).unwrap();
+        std::fs::create_dir_all(root.join(".yarn/releases")).unwrap();
+        std::fs::write(root.join(".pnp.cjs"), "").unwrap();
+
+        let expected = vec![root.join(".yarn/unplugged"), root.join(".pnp.cjs")];
+        std::fs::create_dir(root.join(".git")).unwrap();
+        assert_eq!(package_manager_artifacts(root), expected);
+
+        std::fs::write(root.join(".gitignore"), ".yarn/cache\n").unwrap();
+        let mut with_cache = vec![root.join(".yarn/cache")];
+        with_cache.extend(expected);
+        assert_eq!(package_manager_artifacts(root), with_cache);
+    }
+}
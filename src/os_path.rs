@@ -0,0 +1,161 @@
+//! Handling of non-UTF-8 paths: byte-exact glob matching, escaping in reports and lossless serialization in manifests
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Start of the private-use characters that invalid bytes map to (U+F700..=U+F7FF)
+const ESCAPED_BYTE_BASE: u32 = 0xF700;
+
+/// Text used for glob matching
+///
+/// Valid UTF-8 is returned as is; each invalid byte maps to one private-use character, so `?` matches exactly one byte
+/// and literal characters in a pattern never match a replaced byte (`to_string_lossy` would merge them into U+FFFD).
+pub fn glob_text(name: &OsStr) -> Cow<'_, str> {
+    if let Some(text) = name.to_str() {
+        return Cow::Borrowed(text);
+    }
+    let mut text = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        text.push_str(chunk.valid());
+        for &byte in chunk.invalid() {
+            text.extend(char::from_u32(ESCAPED_BYTE_BASE + byte as u32));
+        }
+    }
+    Cow::Owned(text)
+}
+
+/// Text used in reports: invalid bytes are written as `\xNN`, valid UTF-8 is kept as is
+pub fn escape(path: &Path) -> Cow<'_, str> {
+    if let Some(text) = path.to_str() {
+        return Cow::Borrowed(text);
+    }
+    let mut text = String::new();
+    for chunk in path.as_os_str().as_encoded_bytes().utf8_chunks() {
+        text.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            text.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    Cow::Owned(text)
+}
+
+/// A path in reports: always serialized as a string, with invalid bytes escaped by [`escape`]
+pub fn serialize_escaped<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&escape(path))
+}
+
+/// Like [`serialize_escaped`], for a list of paths
+pub fn serialize_escaped_all<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(paths.iter().map(|path| escape(path)))
+}
+
+/// Lossless path representation: a string for valid UTF-8, raw bytes otherwise
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Repr<'a> {
+    Text(Cow<'a, str>),
+    Bytes { bytes: Vec<u8> },
+}
+
+impl<'a> Repr<'a> {
+    fn from_path(path: &'a Path) -> Self {
+        match path.to_str() {
+            Some(text) => Repr::Text(Cow::Borrowed(text)),
+            None => Repr::Bytes { bytes: path.as_os_str().as_encoded_bytes().to_vec() },
+        }
+    }
+
+    fn into_path<E: serde::de::Error>(self) -> Result<PathBuf, E> {
+        match self {
+            Repr::Text(text) => Ok(PathBuf::from(text.into_owned())),
+            Repr::Bytes { bytes } => bytes_to_path(bytes).ok_or_else(|| E::custom("path bytes are not valid on this platform")),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn bytes_to_path(bytes: Vec<u8>) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+    Some(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: Vec<u8>) -> Option<PathBuf> {
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Paths in files that are read back, such as manifests; use with `#[serde(with = "crate::os_path")]`
+pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    Repr::from_path(path).serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    Repr::deserialize(deserializer)?.into_path()
+}
+
+/// Lists of paths; use with `#[serde(with = "crate::os_path::many")]`. A single path is accepted too
+pub mod many {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(paths.iter().map(|path| Repr::from_path(path)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PathBuf>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany<'a> {
+            One(Repr<'a>),
+            Many(Vec<Repr<'a>>),
+        }
+
+        match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::One(repr) => Ok(vec![repr.into_path()?]),
+            OneOrMany::Many(reprs) => reprs.into_iter().map(Repr::into_path).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        #[serde(with = "crate::os_path")]
+        path: PathBuf,
+        #[serde(with = "crate::os_path::many")]
+        paths: Vec<PathBuf>,
+    }
+
+    #[test]
+    fn test_utf8_paths_stay_strings() {
+        let record = Record { path: PathBuf::from("/work/app"), paths: vec![PathBuf::from("a")] };
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(json, r#"{"path":"/work/app","paths":["a"]}"#);
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record);
+        let single: Record = serde_json::from_str(r#"{"path":"x","paths":"y"}"#).unwrap();
+        assert_eq!(single.paths, vec![PathBuf::from("y")]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_non_utf8_paths() {
+        use std::os::unix::ffi::OsStrExt;
+        let name = OsStr::from_bytes(b"caf\xE9.egg-info");
+        let path = Path::new("/work").join(name);
+
+        assert_eq!(escape(&path), "/work/caf\\xE9.egg-info");
+        let text = glob_text(name);
+        let pattern = glob::Pattern::new("caf?.egg-info").unwrap();
+        assert!(pattern.matches(&text));
+        assert!(!glob::Pattern::new("caf\u{FFFD}.egg-info").unwrap().matches(&text));
+
+        let record = Record { path: path.clone(), paths: vec![path.clone()] };
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains(r#""path":{"bytes":["#));
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record);
+    }
+}
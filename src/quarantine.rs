@@ -0,0 +1,227 @@
+use crate::delete::purge_tree;
+use crate::utils::unix_now;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+const ENTRY_FILE: &str = "entry.json";
+const DATA_DIR: &str = "data";
+
+static ENTRY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Metadata of one quarantine entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantineEntry {
+    /// Original location of the build artifact
+    #[serde(with = "crate::os_path")]
+    pub original_path: PathBuf,
+    /// Project directory the artifact belongs to
+    #[serde(with = "crate::os_path")]
+    pub project: PathBuf,
+    pub quarantined_at: u64,
+    pub expires_at: u64,
+}
+
+/// Result of a restore
+#[derive(Debug, Default)]
+pub struct RestoreReport {
+    pub restored: Vec<PathBuf>,
+    /// Paths not restored because their original location is taken
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Quarantine: build artifacts are moved here and kept for a while, to be restored or purged once expired
+#[derive(Debug, Clone)]
+pub struct Quarantine {
+    root: PathBuf,
+    retention: Duration,
+}
+
+impl Quarantine {
+    pub fn new(root: PathBuf, retention: Duration) -> Self {
+        Self { root, retention }
+    }
+
+    /// Default quarantine `~/.rs_clean/quarantine`
+    pub fn default_root() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".rs_clean").join("quarantine"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Move `path`, which belongs to `project`, into quarantine and return the entry directory
+    ///
+    /// `path` isn't necessarily directly inside the project (e.g. `pkg/__pycache__` or a relocated Cargo target directory),
+    /// so the project directory is recorded separately and `restore <project>` matches on it.
+    pub fn store(&self, path: &Path, project: &Path) -> io::Result<PathBuf> {
+        // 使用规范化后的路径，保证 `restore` 时能按项目路径匹配
+        let absolute = std::path::absolute(path)?;
+        let original_path = match (absolute.parent(), absolute.file_name()) {
+            (Some(parent), Some(name)) => parent.canonicalize()?.join(name),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Path has no parent directory",
+                ))
+            }
+        };
+        let project = std::path::absolute(project)?;
+        let project = project.canonicalize().unwrap_or(project);
+
+        let now = unix_now();
+        let entry_dir = self.root.join(format!(
+            "{}-{}-{}",
+            now,
+            std::process::id(),
+            ENTRY_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&entry_dir)?;
+
+        let entry = QuarantineEntry {
+            original_path,
+            project,
+            quarantined_at: now,
+            expires_at: now.saturating_add(self.retention.as_secs()),
+        };
+        std::fs::write(entry_dir.join(ENTRY_FILE), serde_json::to_vec_pretty(&entry)?)?;
+
+        // 只做重命名：隔离区必须与产物位于同一文件系统
+        if let Err(e) = std::fs::rename(path, entry_dir.join(DATA_DIR)) {
+            let _ = std::fs::remove_dir_all(&entry_dir);
+            return Err(io::Error::new(
+                e.kind(),
+                format!("Cannot move into quarantine {}: {}", self.root.display(), e),
+            ));
+        }
+        Ok(entry_dir)
+    }
+
+    /// List every entry in quarantine
+    pub fn entries(&self) -> io::Result<Vec<(PathBuf, QuarantineEntry)>> {
+        let mut entries = vec![];
+        let dir = match std::fs::read_dir(&self.root) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(entries),
+            Err(e) => return Err(e),
+        };
+        for item in dir.flatten() {
+            let entry_dir = item.path();
+            if let Ok(entry) = Self::read_entry(&entry_dir) {
+                entries.push((entry_dir, entry));
+            }
+        }
+        entries.sort_by_key(|(_, entry)| entry.quarantined_at);
+        Ok(entries)
+    }
+
+    /// Read the metadata in an entry directory
+    pub fn read_entry(entry_dir: &Path) -> io::Result<QuarantineEntry> {
+        let content = std::fs::read(entry_dir.join(ENTRY_FILE))?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// Move an entry back to its original location; returns `Ok(false)` when that location already exists
+    pub fn restore_entry(entry_dir: &Path, entry: &QuarantineEntry) -> io::Result<bool> {
+        if std::fs::symlink_metadata(&entry.original_path).is_ok() {
+            return Ok(false);
+        }
+        if let Some(parent) = entry.original_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::rename(entry_dir.join(DATA_DIR), &entry.original_path)?;
+        std::fs::remove_dir_all(entry_dir)?;
+        Ok(true)
+    }
+
+    /// Restore all quarantined artifacts of a project
+    pub fn restore_project(&self, project: &Path) -> io::Result<RestoreReport> {
+        let project = std::path::absolute(project)?;
+        let project = project.canonicalize().unwrap_or(project);
+
+        let mut report = RestoreReport::default();
+        for (entry_dir, entry) in self.entries()? {
+            if entry.project != project {
+                continue;
+            }
+            if Self::restore_entry(&entry_dir, &entry)? {
+                report.restored.push(entry.original_path);
+            } else {
+                report.skipped.push(entry.original_path);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Purge entries past their retention period (all of them when `all` is true) and return the purged entries
+    pub fn purge(&self, all: bool, threads: usize) -> io::Result<Vec<QuarantineEntry>> {
+        let now = unix_now();
+        let mut purged = vec![];
+        for (entry_dir, entry) in self.entries()? {
+            if all || entry.expires_at <= now {
+                purge_tree(&entry_dir, threads)?;
+                purged.push(entry);
+            }
+        }
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_restore_project() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("ml/.venv/lib")).unwrap();
+        let project = dir.path().join("ml").canonicalize().unwrap();
+        std::fs::write(project.join(".venv/lib/torch.so"), "x").unwrap();
+
+        let quarantine = Quarantine::new(dir.path().join("q"), Duration::from_secs(3600));
+        quarantine.store(&project.join(".venv"), &project).unwrap();
+        assert!(!project.join(".venv").exists());
+
+        let entries = quarantine.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].1.project, project);
+
+        let report = quarantine.restore_project(&project).unwrap();
+        assert_eq!(report.restored, vec![project.join(".venv")]);
+        assert!(project.join(".venv/lib/torch.so").exists());
+        assert!(quarantine.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_restore_nested_artifact() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("app/pkg/__pycache__")).unwrap();
+        let project = dir.path().join("app").canonicalize().unwrap();
+
+        let quarantine = Quarantine::new(dir.path().join("q"), Duration::from_secs(3600));
+        quarantine.store(&project.join("pkg/__pycache__"), &project).unwrap();
+        let report = quarantine.restore_project(&dir.path().join("app")).unwrap();
+        assert_eq!(report.restored, vec![project.join("pkg/__pycache__")]);
+        assert!(project.join("pkg/__pycache__").is_dir());
+    }
+
+    #[test]
+    fn test_purge_respects_retention() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("a/build")).unwrap();
+        std::fs::create_dir_all(dir.path().join("b/build")).unwrap();
+
+        let expired = Quarantine::new(dir.path().join("q"), Duration::ZERO);
+        expired.store(&dir.path().join("a/build"), &dir.path().join("a")).unwrap();
+        let kept = Quarantine::new(dir.path().join("q"), Duration::from_secs(3600));
+        kept.store(&dir.path().join("b/build"), &dir.path().join("b")).unwrap();
+
+        assert_eq!(kept.purge(false, 2).unwrap().len(), 1);
+        assert_eq!(kept.entries().unwrap().len(), 1);
+        assert_eq!(kept.purge(true, 2).unwrap().len(), 1);
+        assert!(kept.entries().unwrap().is_empty());
+    }
+}
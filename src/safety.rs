@@ -0,0 +1,186 @@
+use crate::config::ConfigError;
+use crate::constant::{DEFAULT_PROTECTED_DIRS, DEFAULT_PROTECTED_ROOTS};
+use crate::os_path::escape;
+use std::path::{Path, PathBuf};
+
+/// Decides which directories may be used as cleaning roots
+///
+/// Checked in order: the filesystem root, the home directory, protected trees (built-in defaults plus the user's list),
+/// direct children of the filesystem root, the built-in protected directories themselves, and the allowlist.
+/// All rules are skipped when `allow_root` is true.
+#[derive(Debug, Clone, Default)]
+pub struct SafetyPolicy {
+    /// Extra protected directories from the user; neither they nor anything below them can be cleaned
+    pub protected_roots: Vec<PathBuf>,
+    /// When not empty, a cleaning root must be inside one of these directories
+    pub allowed_roots: Vec<PathBuf>,
+    /// Explicitly override every rule
+    pub allow_root: bool,
+}
+
+impl SafetyPolicy {
+    /// Canonicalize `path` and check whether it may be cleaned, returning the canonical path
+    pub fn check(&self, path: &Path) -> Result<PathBuf, ConfigError> {
+        if path.as_os_str().is_empty() {
+            return Err(ConfigError::InvalidConfig("Path cannot be empty".to_string()));
+        }
+        let canonical = path.canonicalize().map_err(|_| {
+            ConfigError::InvalidConfig(format!(
+                "Path '{}' does not exist or cannot be accessed",
+                path.display()
+            ))
+        })?;
+        if self.allow_root {
+            return Ok(canonical);
+        }
+
+        let blocked = |rule: String| ConfigError::PathBlocked {
+            path: canonical.clone(),
+            rule,
+        };
+
+        if canonical.parent().is_none() {
+            return Err(blocked("it is the filesystem root".to_string()));
+        }
+        // 主目录本身不能清理，其下的项目仍然可以
+        if let Some(home) = dirs::home_dir().filter(|home| is_same(&canonical, home)) {
+            return Err(blocked(format!("it is the home directory '{}'", escape(&home))));
+        }
+
+        let builtin = DEFAULT_PROTECTED_ROOTS.iter().map(|root| (PathBuf::from(root), "built-in protected root"));
+        let user = self.protected_roots.iter().map(|root| (root.clone(), "protected_roots"));
+        for (root, source) in builtin.chain(user) {
+            if is_within(&canonical, &root) {
+                return Err(blocked(format!("it is within '{}' ({})", escape(&root), source)));
+            }
+        }
+        // 以下规则只拒绝目录本身，其下的项目仍然可以清理
+        if canonical.parent().is_some_and(|parent| parent.parent().is_none()) {
+            return Err(blocked("it is a top-level directory of the filesystem".to_string()));
+        }
+        if let Some(dir) = DEFAULT_PROTECTED_DIRS.iter().map(Path::new).find(|dir| is_same(&canonical, dir)) {
+            return Err(blocked(format!("it is '{}' (built-in protected directory)", escape(dir))));
+        }
+
+        if !self.allowed_roots.is_empty() && !self.allowed_roots.iter().any(|root| is_within(&canonical, root)) {
+            let allowed: Vec<_> = self.allowed_roots.iter().map(|root| escape(root)).collect();
+            return Err(blocked(format!(
+                "it is outside every allowed root (allowed_roots: {})",
+                allowed.join(", ")
+            )));
+        }
+
+        Ok(canonical)
+    }
+}
+
+/// Whether `path` (canonical) is `root` or below it; `root` is canonicalized too when it exists
+fn is_within(path: &Path, root: &Path) -> bool {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    if cfg!(windows) {
+        let path = path.to_string_lossy().to_lowercase();
+        let root = root.to_string_lossy().to_lowercase();
+        Path::new(&path).starts_with(Path::new(&root))
+    } else {
+        path.starts_with(&root)
+    }
+}
+
+/// Whether `path` (canonical) is `dir` itself
+fn is_same(path: &Path, dir: &Path) -> bool {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    is_within(path, &dir) && path.components().count() == dir.components().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_protected_roots() {
+        let policy = SafetyPolicy::default();
+        let err = policy.check(Path::new("/etc")).unwrap_err().to_string();
+        assert!(err.contains("within '/etc' (built-in protected root)"), "{}", err);
+        assert!(policy.check(Path::new("/usr/bin")).is_err());
+        assert!(policy.check(Path::new("/")).unwrap_err().to_string().contains("filesystem root"));
+
+        // 临时目录不再被一刀切地拒绝
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(policy.check(dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_home_directory_itself_is_protected() {
+        let Some(home) = dirs::home_dir().filter(|home| home.is_dir()) else {
+            return;
+        };
+        let policy = SafetyPolicy::default();
+        let err = policy.check(&home).unwrap_err().to_string();
+        assert!(err.contains("it is the home directory"), "{}", err);
+
+        // 主目录下的项目仍然可以清理
+        let project = tempfile::TempDir::new_in(&home).unwrap();
+        assert!(policy.check(project.path()).is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_top_level_directories_are_protected() {
+        let policy = SafetyPolicy::default();
+        for dir in ["/var", "/tmp"] {
+            let err = policy.check(Path::new(dir)).unwrap_err().to_string();
+            assert!(err.contains("top-level directory"), "{}", err);
+        }
+
+        let dir = tempfile::TempDir::new_in("/tmp").unwrap();
+        assert!(policy.check(dir.path()).is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_builtin_protected_dirs_are_exact() {
+        if !Path::new("/var/tmp").is_dir() {
+            return;
+        }
+        let policy = SafetyPolicy::default();
+        let err = policy.check(Path::new("/var/tmp")).unwrap_err().to_string();
+        assert!(err.contains("it is '/var/tmp' (built-in protected directory)"), "{}", err);
+
+        let Ok(dir) = tempfile::TempDir::new_in("/var/tmp") else {
+            return;
+        };
+        assert!(policy.check(dir.path()).is_ok());
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_private_var_is_protected() {
+        let policy = SafetyPolicy::default();
+        let err = policy.check(Path::new("/var")).unwrap_err().to_string();
+        assert!(err.contains("it is '/private/var' (built-in protected directory)"), "{}", err);
+    }
+
+    #[test]
+    fn test_user_protected_and_allowed_roots() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let projects = dir.path().join("projects");
+        let release = projects.join("release");
+        std::fs::create_dir_all(&release).unwrap();
+        std::fs::create_dir_all(dir.path().join("other")).unwrap();
+
+        let policy = SafetyPolicy {
+            protected_roots: vec![release.clone()],
+            allowed_roots: vec![projects.clone()],
+            allow_root: false,
+        };
+        assert!(policy.check(&projects).is_ok());
+        let err = policy.check(&release).unwrap_err().to_string();
+        assert!(err.contains("(protected_roots)"), "{}", err);
+        let err = policy.check(&dir.path().join("other")).unwrap_err().to_string();
+        assert!(err.contains("outside every allowed root"), "{}", err);
+
+        let policy = SafetyPolicy { allow_root: true, ..policy };
+        assert!(policy.check(&release).is_ok());
+        assert!(policy.check(&dir.path().join("missing")).is_err());
+    }
+}
@@ -0,0 +1,485 @@
+use crate::cargo;
+use crate::cmd::{Cmd, CommandType};
+use crate::os_path;
+use crate::size::{HardLinkTracker, SizeBudget, SizeEngine};
+use crate::{discover_roots, format_size, get_cpu_core_count, measure_projects, CleanRoot, Discovery};
+use clap::{Args, ValueEnum};
+use colored::*;
+use serde::{Serialize, Serializer};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Sort order of the scan report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SortKey {
+    /// Largest reclaimable size first
+    #[default]
+    Size,
+    /// Least recently built first
+    Age,
+    /// Alphabetical by project path
+    Path,
+}
+
+/// Output format of the scan report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+/// Options for the `scan` subcommand
+#[derive(Debug, Clone, Default, Args)]
+pub struct ScanOptions {
+    /// Sort projects by size, age or path
+    #[clap(long, value_enum, default_value_t = SortKey::Size)]
+    pub sort: SortKey,
+
+    /// Only show the first N projects after sorting
+    #[clap(long, value_parser)]
+    pub top: Option<usize>,
+
+    /// Group projects by ecosystem
+    #[clap(long, action)]
+    pub group: bool,
+
+    /// Output format
+    #[clap(long, value_enum, default_value_t = OutputFormat::Table)]
+    pub format: OutputFormat,
+}
+
+/// Reclaimable space of a single detected project
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectReport {
+    /// Scan root the project was found under
+    #[serde(serialize_with = "os_path::serialize_escaped")]
+    pub root: PathBuf,
+    #[serde(serialize_with = "os_path::serialize_escaped")]
+    pub path: PathBuf,
+    pub ecosystem: CommandType,
+    #[serde(serialize_with = "os_path::serialize_escaped_all")]
+    pub artifact_dirs: Vec<PathBuf>,
+    /// Bytes actually allocated on disk, hard links counted once
+    pub reclaimable_bytes: u64,
+    /// Sum of file lengths
+    pub apparent_bytes: u64,
+    pub file_count: u64,
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub last_build: Option<SystemTime>,
+    /// Size of each Cargo profile and documentation directory, whether selected for cleaning or not
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<ProfileReport>,
+}
+
+/// Size of a single Cargo profile directory (`target/debug`, `target/<triple>/release`, `target/doc`, ...)
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileReport {
+    #[serde(serialize_with = "os_path::serialize_escaped")]
+    pub path: PathBuf,
+    pub reclaimable_bytes: u64,
+    pub apparent_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct GroupReport<'a> {
+    ecosystem: CommandType,
+    reclaimable_bytes: u64,
+    apparent_bytes: u64,
+    projects: Vec<&'a ProjectReport>,
+}
+
+fn serialize_timestamp<S: Serializer>(time: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error> {
+    match time.and_then(|t| t.duration_since(UNIX_EPOCH).ok()) {
+        Some(d) => serializer.serialize_some(&d.as_secs()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Scan every root for projects and measure their artifact directories without cleaning anything
+pub async fn scan_projects(
+    roots: &[CleanRoot],
+    commands: &[Cmd],
+    max_concurrent: Option<usize>,
+    project_budget: SizeBudget,
+    artifact_budget: SizeBudget,
+) -> Vec<ProjectReport> {
+    let (Discovery { projects, ignores, .. }, owners) = discover_roots(roots, commands);
+    let engine = SizeEngine::new(max_concurrent.unwrap_or_else(get_cpu_core_count));
+    let hard_links = Arc::new(HardLinkTracker::default());
+
+    let to_measure: Vec<(&Cmd, &Path)> = projects
+        .iter()
+        .map(|(path, cmd_type)| {
+            let cmd = commands.iter().find(|c| c.command_type == *cmd_type).unwrap();
+            (cmd, path.as_path())
+        })
+        .collect();
+    let sizes = measure_projects(&engine, &to_measure, &ignores, project_budget, artifact_budget, &hard_links).await;
+    let mut profiles = cargo_profiles(&engine, &projects).await.into_iter();
+
+    projects
+        .into_iter()
+        .zip(sizes)
+        .zip(owners)
+        .map(|(((path, cmd_type), size), owner)| ProjectReport {
+            profiles: if cmd_type == CommandType::Cargo { profiles.next().unwrap_or_default() } else { vec![] },
+            root: roots[owner].path.clone(),
+            path,
+            ecosystem: cmd_type,
+            artifact_dirs: size.artifact_dirs,
+            reclaimable_bytes: size.stats.disk_size,
+            apparent_bytes: size.stats.size,
+            file_count: size.stats.file_count,
+            last_build: size.stats.last_modified,
+        })
+        .collect()
+}
+
+/// Per-profile sizes of every Cargo project, in project order
+async fn cargo_profiles(engine: &SizeEngine, projects: &[(PathBuf, CommandType)]) -> Vec<Vec<ProfileReport>> {
+    let dirs: Vec<Vec<PathBuf>> = projects
+        .iter()
+        .filter(|(_, cmd_type)| *cmd_type == CommandType::Cargo)
+        .map(|(path, _)| cargo::breakdown_dirs(&cargo::target_dir(path)))
+        .collect();
+    let roots = dirs.iter().flatten().map(|dir| (dir.clone(), SizeBudget::UNLIMITED)).collect();
+    // profile 之间的硬链接各自计入，与清理时的统计一致
+    let mut stats = engine.clone().measure_all_async(roots, Arc::new(HardLinkTracker::default())).await.into_iter();
+    dirs.into_iter()
+        .map(|dirs| {
+            dirs.into_iter()
+                .zip(stats.by_ref())
+                .map(|(path, stats)| ProfileReport { path, reclaimable_bytes: stats.disk_size, apparent_bytes: stats.size })
+                .collect()
+        })
+        .collect()
+}
+
+/// Sort the reports in place and keep at most `top` entries
+pub fn sort_reports(reports: &mut Vec<ProjectReport>, sort: SortKey, top: Option<usize>) {
+    match sort {
+        SortKey::Size => reports.sort_by_key(|r| std::cmp::Reverse(r.reclaimable_bytes)),
+        // 从未构建过的项目排在最后
+        SortKey::Age => reports.sort_by_key(|r| (r.last_build.is_none(), r.last_build)),
+        SortKey::Path => reports.sort_by(|a, b| a.path.cmp(&b.path)),
+    }
+    if let Some(top) = top {
+        reports.truncate(top);
+    }
+}
+
+fn group_reports(reports: &[ProjectReport]) -> Vec<GroupReport<'_>> {
+    let mut groups: Vec<GroupReport> = vec![];
+    for report in reports {
+        match groups.iter_mut().find(|g| g.ecosystem == report.ecosystem) {
+            Some(group) => {
+                group.reclaimable_bytes += report.reclaimable_bytes;
+                group.apparent_bytes += report.apparent_bytes;
+                group.projects.push(report);
+            }
+            None => groups.push(GroupReport {
+                ecosystem: report.ecosystem,
+                reclaimable_bytes: report.reclaimable_bytes,
+                apparent_bytes: report.apparent_bytes,
+                projects: vec![report],
+            }),
+        }
+    }
+    groups
+}
+
+/// Render the reports in the requested format
+pub fn render_reports(reports: &[ProjectReport], options: &ScanOptions) -> String {
+    match options.format {
+        OutputFormat::Table => render_table(reports, options.group),
+        OutputFormat::Json => {
+            let json = if options.group {
+                serde_json::to_string_pretty(&group_reports(reports))
+            } else {
+                serde_json::to_string_pretty(reports)
+            };
+            json.expect("Failed to serialize scan report")
+        }
+        OutputFormat::Csv => render_csv(reports, options.group),
+    }
+}
+
+fn format_age(time: Option<SystemTime>) -> String {
+    let Some(elapsed) = time.and_then(|t| t.elapsed().ok()) else {
+        return "-".to_string();
+    };
+    let secs = elapsed.as_secs();
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+fn artifact_names(report: &ProjectReport) -> Vec<String> {
+    report
+        .artifact_dirs
+        .iter()
+        .map(|p| {
+            os_path::escape(p.strip_prefix(&report.path).unwrap_or(p)).into_owned()
+        })
+        .collect()
+}
+
+fn render_table_rows(out: &mut String, reports: &[&ProjectReport]) {
+    for report in reports {
+        out.push_str(&format!(
+            "{:>10}  {:>10}  {:>8}  {:<10}  {:<8}  {}  {}\n",
+            format_size(report.reclaimable_bytes),
+            format_size(report.apparent_bytes),
+            report.file_count,
+            format_age(report.last_build),
+            report.ecosystem.as_str(),
+            os_path::escape(&report.path),
+            artifact_names(report).join(", ").dimmed()
+        ));
+        for profile in &report.profiles {
+            out.push_str(&format!(
+                "{:>10}  {:>10}  {:>8}  {:<10}  {:<8}    {}\n",
+                format_size(profile.reclaimable_bytes),
+                format_size(profile.apparent_bytes),
+                "",
+                "",
+                "",
+                os_path::escape(profile.path.strip_prefix(&report.path).unwrap_or(&profile.path)).dimmed()
+            ));
+        }
+    }
+}
+
+fn render_table(reports: &[ProjectReport], group: bool) -> String {
+    let mut out = format!(
+        "{}\n",
+        format!(
+            "{:>10}  {:>10}  {:>8}  {:<10}  {:<8}  {}  {}",
+            "SIZE", "APPARENT", "FILES", "LAST BUILD", "TYPE", "PATH", "ARTIFACTS"
+        )
+        .bold()
+    );
+
+    if group {
+        for group in group_reports(reports) {
+            out.push_str(&format!(
+                "\n{} ({} projects, {})\n",
+                group.ecosystem.as_str().blue().bold(),
+                group.projects.len(),
+                format_size(group.reclaimable_bytes).cyan()
+            ));
+            render_table_rows(&mut out, &group.projects);
+        }
+    } else {
+        render_table_rows(&mut out, &reports.iter().collect::<Vec<_>>());
+    }
+
+    let mut roots: Vec<(&Path, usize, u64)> = vec![];
+    for report in reports {
+        match roots.iter_mut().find(|(root, _, _)| *root == report.root) {
+            Some((_, count, bytes)) => {
+                *count += 1;
+                *bytes += report.reclaimable_bytes;
+            }
+            None => roots.push((&report.root, 1, report.reclaimable_bytes)),
+        }
+    }
+    if roots.len() > 1 {
+        out.push('\n');
+        for (root, count, bytes) in roots {
+            out.push_str(&format!("{} - {} projects, {}\n", os_path::escape(root), count, format_size(bytes).cyan()));
+        }
+    }
+
+    let total: u64 = reports.iter().map(|r| r.reclaimable_bytes).sum();
+    let total_apparent: u64 = reports.iter().map(|r| r.apparent_bytes).sum();
+    out.push_str(&format!(
+        "\n{} projects, {} reclaimable on disk ({} apparent)\n",
+        reports.len(),
+        format_size(total).green().bold(),
+        format_size(total_apparent)
+    ));
+    out
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render_csv(reports: &[ProjectReport], group: bool) -> String {
+    let mut rows: Vec<&ProjectReport> = reports.iter().collect();
+    if group {
+        rows = group_reports(reports)
+            .into_iter()
+            .flat_map(|g| g.projects)
+            .collect();
+    }
+
+    let mut out = String::from("root,path,ecosystem,reclaimable_bytes,apparent_bytes,file_count,last_build,artifact_dirs\n");
+    for report in rows {
+        let last_build = report
+            .last_build
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs().to_string())
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            csv_field(&os_path::escape(&report.root)),
+            csv_field(&os_path::escape(&report.path)),
+            report.ecosystem.as_str(),
+            report.reclaimable_bytes,
+            report.apparent_bytes,
+            report.file_count,
+            last_build,
+            csv_field(&artifact_names(report).join(";"))
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WalkOptions;
+    use std::time::Duration;
+
+    fn report(path: &str, ecosystem: CommandType, bytes: u64, built_secs: Option<u64>) -> ProjectReport {
+        ProjectReport {
+            root: PathBuf::from("."),
+            path: PathBuf::from(path),
+            ecosystem,
+            artifact_dirs: vec![PathBuf::from(path).join("target")],
+            reclaimable_bytes: bytes,
+            apparent_bytes: bytes,
+            file_count: 1,
+            last_build: built_secs.map(|s| UNIX_EPOCH + Duration::from_secs(s)),
+            profiles: vec![],
+        }
+    }
+
+    fn sample() -> Vec<ProjectReport> {
+        vec![
+            report("b", CommandType::Cargo, 10, Some(300)),
+            report("a", CommandType::NodeJs, 30, None),
+            report("c", CommandType::Cargo, 20, Some(100)),
+        ]
+    }
+
+    fn paths(reports: &[ProjectReport]) -> Vec<String> {
+        reports.iter().map(|r| r.path.display().to_string()).collect()
+    }
+
+    #[test]
+    fn test_sort_reports() {
+        let mut reports = sample();
+        sort_reports(&mut reports, SortKey::Size, None);
+        assert_eq!(paths(&reports), vec!["a", "c", "b"]);
+
+        sort_reports(&mut reports, SortKey::Age, None);
+        assert_eq!(paths(&reports), vec!["c", "b", "a"]);
+
+        sort_reports(&mut reports, SortKey::Path, Some(2));
+        assert_eq!(paths(&reports), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_group_reports() {
+        let reports = sample();
+        let groups = group_reports(&reports);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].ecosystem, CommandType::Cargo);
+        assert_eq!(groups[0].reclaimable_bytes, 30);
+        assert_eq!(groups[0].projects.len(), 2);
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let reports = vec![report("dir,with,commas", CommandType::Cargo, 42, Some(7))];
+        let csv = render_csv(&reports, false);
+        assert_eq!(
+            csv,
+            "root,path,ecosystem,reclaimable_bytes,apparent_bytes,file_count,last_build,artifact_dirs\n.,\"dir,with,commas\",cargo,42,42,1,7,target\n"
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let options = ScanOptions { format: OutputFormat::Json, ..Default::default() };
+        let json: serde_json::Value = serde_json::from_str(&render_reports(&sample(), &options)).unwrap();
+        assert_eq!(json[0]["ecosystem"], "cargo");
+        assert_eq!(json[1]["last_build"], serde_json::Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_scan_projects_measures_artifacts_only() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let project = dir.path().join("app");
+        std::fs::create_dir_all(project.join("target/debug/.fingerprint")).unwrap();
+        std::fs::write(project.join("Cargo.toml"), "[package]").unwrap();
+        std::fs::write(project.join("target/debug/app"), vec![0u8; 128]).unwrap();
+
+        let commands = vec![Cmd::new(CommandType::Cargo, vec!["Cargo.toml"])];
+        let roots = [CleanRoot::new(dir.path(), WalkOptions::default())];
+        let reports = scan_projects(&roots, &commands, Some(1), SizeBudget::UNLIMITED, SizeBudget::UNLIMITED).await;
+
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].path, project);
+        assert_eq!(reports[0].artifact_dirs, vec![project.join("target")]);
+        assert_eq!(reports[0].apparent_bytes, 128);
+        assert_eq!(reports[0].file_count, 1);
+        assert!(reports[0].last_build.is_some());
+        let profiles: Vec<_> = reports[0].profiles.iter().map(|p| (p.path.clone(), p.apparent_bytes)).collect();
+        assert_eq!(profiles, vec![(project.join("target/debug"), 128)]);
+        // 扫描不应删除任何内容
+        assert!(project.join("target/debug/app").exists());
+    }
+
+    #[tokio::test]
+    async fn test_scan_projects_across_roots() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["a/app", "b/app"] {
+            let project = dir.path().join(name);
+            std::fs::create_dir_all(project.join("target")).unwrap();
+            std::fs::write(project.join("Cargo.toml"), "[package]").unwrap();
+            std::fs::write(project.join("target/out"), vec![0u8; 16]).unwrap();
+        }
+
+        let commands = vec![Cmd::new(CommandType::Cargo, vec!["Cargo.toml"])];
+        let roots = [
+            CleanRoot::new(dir.path().join("a"), WalkOptions::default()),
+            CleanRoot::new(dir.path().join("b"), WalkOptions::default()),
+        ];
+        let reports = scan_projects(&roots, &commands, Some(2), SizeBudget::UNLIMITED, SizeBudget::UNLIMITED).await;
+
+        let owners: Vec<_> = reports.iter().map(|r| r.root.clone()).collect();
+        assert_eq!(owners, vec![dir.path().join("a"), dir.path().join("b")]);
+        let table = render_table(&reports, false);
+        assert!(table.contains(&format!("{} - 1 projects", dir.path().join("b").display())));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_render_escapes_non_utf8_paths() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new("/work").join(std::ffi::OsStr::from_bytes(b"app\xFF"));
+        let mut reports = sample();
+        reports[0].path = path.clone();
+        reports[0].artifact_dirs = vec![path.join("target")];
+
+        let options = ScanOptions { format: OutputFormat::Json, ..Default::default() };
+        let json: serde_json::Value = serde_json::from_str(&render_reports(&reports, &options)).unwrap();
+        assert_eq!(json[0]["path"], "/work/app\\xFF");
+        assert_eq!(json[0]["artifact_dirs"][0], "/work/app\\xFF/target");
+        assert!(render_csv(&reports, false).contains("/work/app\\xFF,cargo"));
+    }
+}
@@ -0,0 +1,532 @@
+use colored::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::SystemTime;
+
+/// Directory statistics: apparent size, disk usage, file count and the latest modification time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirStats {
+    pub size: u64,
+    pub disk_size: u64,
+    pub file_count: u64,
+    pub last_modified: Option<SystemTime>,
+    /// Measuring stopped early because the budget ran out
+    pub truncated: bool,
+}
+
+impl DirStats {
+    fn add_file(&mut self, metadata: &std::fs::Metadata, hard_links: &HardLinkTracker) {
+        // 同一个 inode 的多个硬链接只统计一次
+        if !hard_links.first_sighting(metadata) {
+            return;
+        }
+        self.size += metadata.len();
+        self.disk_size += allocated_size(metadata);
+        self.file_count += 1;
+        if let Ok(modified) = metadata.modified() {
+            self.last_modified = self.last_modified.max(Some(modified));
+        }
+    }
+
+    pub fn merge(&mut self, other: &DirStats) {
+        self.size += other.size;
+        self.disk_size += other.disk_size;
+        self.file_count += other.file_count;
+        self.last_modified = self.last_modified.max(other.last_modified);
+        self.truncated |= other.truncated;
+    }
+
+    /// What decreased between two measurements
+    pub fn saturating_sub(&self, other: &DirStats) -> DirStats {
+        DirStats {
+            size: self.size.saturating_sub(other.size),
+            disk_size: self.disk_size.saturating_sub(other.disk_size),
+            file_count: self.file_count.saturating_sub(other.file_count),
+            last_modified: self.last_modified,
+            truncated: self.truncated || other.truncated,
+        }
+    }
+}
+
+/// Remembers the `(dev, inode)` pairs already counted so hard-linked files are counted once per run
+#[derive(Debug, Default)]
+pub struct HardLinkTracker {
+    seen: Mutex<HashSet<(u64, u64)>>,
+}
+
+impl HardLinkTracker {
+    /// Returns true the first time a file is seen; files with a single link aren't recorded
+    #[cfg(unix)]
+    fn first_sighting(&self, metadata: &std::fs::Metadata) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        if metadata.nlink() <= 1 {
+            return true;
+        }
+        self.seen
+            .lock()
+            .unwrap()
+            .insert((metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    fn first_sighting(&self, _metadata: &std::fs::Metadata) -> bool {
+        true
+    }
+}
+
+/// Disk space a file actually takes (by allocated blocks, so sparse files aren't overestimated)
+#[cfg(unix)]
+fn allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    // st_blocks 始终以 512 字节为单位
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &std::fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Depth and file-count budget one measured root may consume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeBudget {
+    pub max_depth: usize,
+    pub max_files: usize,
+}
+
+impl SizeBudget {
+    pub const UNLIMITED: SizeBudget = SizeBudget {
+        max_depth: usize::MAX,
+        max_files: usize::MAX,
+    };
+}
+
+struct WorkItem {
+    root: usize,
+    path: PathBuf,
+    depth: usize,
+}
+
+/// Budget state of one root, shared by all worker threads
+struct RootState {
+    budget: SizeBudget,
+    files: AtomicU64,
+    exhausted: AtomicBool,
+    depth_exceeded: AtomicBool,
+}
+
+/// Parallel work-stealing directory size engine
+///
+/// Each worker keeps a local queue of directories and steals from the front of other workers' queues when its own is empty.
+/// Every directory is read once: subdirectories are recognized by `d_type` without a stat, but each file still needs its own
+/// `DirEntry::metadata` call (there is no batched stat). All of it runs synchronously on the workers, with no future per entry.
+///
+/// Clones of the engine share one pool of worker slots: concurrent measurements use at most `threads` workers in total,
+/// take only the remaining slots when fewer are free, and wait when none are.
+#[derive(Debug, Clone)]
+pub struct SizeEngine {
+    threads: usize,
+    permits: Arc<WorkerPermits>,
+}
+
+/// Worker slots shared by all measurements
+#[derive(Debug)]
+struct WorkerPermits {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl WorkerPermits {
+    /// Take at least 1 and at most `wanted` slots
+    fn acquire(&self, wanted: usize) -> usize {
+        let mut available = self.available.lock().unwrap();
+        while *available == 0 {
+            available = self.released.wait(available).unwrap();
+        }
+        let granted = wanted.min(*available);
+        *available -= granted;
+        granted
+    }
+
+    fn release(&self, count: usize) {
+        *self.available.lock().unwrap() += count;
+        self.released.notify_all();
+    }
+}
+
+impl SizeEngine {
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        Self {
+            threads,
+            permits: Arc::new(WorkerPermits { available: Mutex::new(threads), released: Condvar::new() }),
+        }
+    }
+
+    /// Measure a single path
+    pub fn measure(&self, root: PathBuf, budget: SizeBudget, hard_links: &HardLinkTracker) -> DirStats {
+        self.measure_all(vec![(root, budget)], hard_links)
+            .pop()
+            .unwrap_or_default()
+    }
+
+    /// Measure several roots with one thread pool; results are in input order
+    pub fn measure_all(&self, roots: Vec<(PathBuf, SizeBudget)>, hard_links: &HardLinkTracker) -> Vec<DirStats> {
+        let mut results = vec![DirStats::default(); roots.len()];
+        let mut initial = VecDeque::new();
+        let mut states = Vec::with_capacity(roots.len());
+
+        for (index, (path, budget)) in roots.into_iter().enumerate() {
+            states.push(RootState {
+                budget,
+                files: AtomicU64::new(0),
+                exhausted: AtomicBool::new(false),
+                depth_exceeded: AtomicBool::new(false),
+            });
+            // 不跟随根路径的符号链接：指向共享存储的 `node_modules` 链接只计链接本身
+            match std::fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.is_file() => results[index].add_file(&metadata, hard_links),
+                Ok(metadata) if metadata.is_dir() => initial.push_back(WorkItem { root: index, path, depth: 0 }),
+                _ => {}
+            }
+        }
+
+        if !initial.is_empty() {
+            let threads = self.permits.acquire(self.threads);
+            let queues = WorkQueues::new(threads, initial);
+
+            let partials: Vec<HashMap<usize, DirStats>> = std::thread::scope(|scope| {
+                let handles: Vec<_> = (0..threads)
+                    .map(|worker| {
+                        let queues = &queues;
+                        let states = &states;
+                        scope.spawn(move || run_worker(worker, queues, states, hard_links))
+                    })
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap_or_default()).collect()
+            });
+
+            self.permits.release(threads);
+
+            for partial in partials {
+                for (root, stats) in partial {
+                    results[root].merge(&stats);
+                }
+            }
+        }
+
+        for (stats, state) in results.iter_mut().zip(&states) {
+            if state.depth_exceeded.load(Ordering::Relaxed) || state.exhausted.load(Ordering::Relaxed) {
+                stats.truncated = true;
+            }
+        }
+        results
+    }
+
+    /// Run [`SizeEngine::measure_all`] on the blocking thread pool
+    pub async fn measure_all_async(
+        self,
+        roots: Vec<(PathBuf, SizeBudget)>,
+        hard_links: Arc<HardLinkTracker>,
+    ) -> Vec<DirStats> {
+        let len = roots.len();
+        tokio::task::spawn_blocking(move || self.measure_all(roots, &hard_links))
+            .await
+            .unwrap_or_else(|_| vec![DirStats::default(); len])
+    }
+}
+
+/// Work-stealing task queues with one local queue per worker
+///
+/// When every queue is empty but tasks are still being processed, idle workers wait on a condition variable for new tasks;
+/// once all tasks, including the ones created while processing, are done, [`WorkQueues::next`] returns `None`.
+pub(crate) struct WorkQueues<T> {
+    queues: Vec<Mutex<VecDeque<T>>>,
+    /// Tasks queued but not yet finished
+    pending: AtomicUsize,
+    /// Number of waiting workers
+    idle: Mutex<usize>,
+    wake: Condvar,
+}
+
+impl<T> WorkQueues<T> {
+    /// Initial tasks are dealt round-robin to the workers
+    pub(crate) fn new(workers: usize, initial: impl IntoIterator<Item = T>) -> Self {
+        let queues: Vec<Mutex<VecDeque<T>>> = (0..workers.max(1)).map(|_| Mutex::new(VecDeque::new())).collect();
+        let mut pending = 0;
+        for (i, item) in initial.into_iter().enumerate() {
+            queues[i % queues.len()].lock().unwrap().push_back(item);
+            pending += 1;
+        }
+        Self { queues, pending: AtomicUsize::new(pending), idle: Mutex::new(0), wake: Condvar::new() }
+    }
+
+    /// Take the next task, waiting when there is none; returns `None` once all tasks are done
+    pub(crate) fn next(&self, worker: usize) -> Option<T> {
+        loop {
+            if let Some(item) = self.take(worker) {
+                return Some(item);
+            }
+            let mut idle = self.idle.lock().unwrap();
+            if self.pending.load(Ordering::Acquire) == 0 {
+                return None;
+            }
+            // 持有锁时再检查一次：入队方在入队之后才会获取锁并唤醒，不会丢失唤醒
+            if let Some(item) = self.take(worker) {
+                return Some(item);
+            }
+            *idle += 1;
+            idle = self.wake.wait(idle).unwrap();
+            *idle -= 1;
+        }
+    }
+
+    /// Put tasks created while processing into `worker`'s local queue
+    pub(crate) fn push(&self, worker: usize, items: Vec<T>) {
+        if items.is_empty() {
+            return;
+        }
+        // 先增加计数再入队，保证 pending 不会提前归零
+        self.pending.fetch_add(items.len(), Ordering::AcqRel);
+        self.queues[worker].lock().unwrap().extend(items);
+        self.wake_idle();
+    }
+
+    /// Mark one task as finished
+    pub(crate) fn done(&self) {
+        if self.pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.wake_idle();
+        }
+    }
+
+    fn take(&self, worker: usize) -> Option<T> {
+        if let Some(item) = self.queues[worker].lock().unwrap().pop_back() {
+            return Some(item);
+        }
+        // 本地队列为空时，从其他线程的队列头部窃取
+        (1..self.queues.len())
+            .map(|offset| (worker + offset) % self.queues.len())
+            .find_map(|victim| self.queues[victim].lock().unwrap().pop_front())
+    }
+
+    fn wake_idle(&self) {
+        if *self.idle.lock().unwrap() > 0 {
+            self.wake.notify_all();
+        }
+    }
+}
+
+fn run_worker(
+    worker: usize,
+    queues: &WorkQueues<WorkItem>,
+    states: &[RootState],
+    hard_links: &HardLinkTracker,
+) -> HashMap<usize, DirStats> {
+    let mut partial: HashMap<usize, DirStats> = HashMap::new();
+    while let Some(item) = queues.next(worker) {
+        let subdirs = visit_dir(&item, &states[item.root], partial.entry(item.root).or_default(), hard_links);
+        queues.push(worker, subdirs);
+        queues.done();
+    }
+    partial
+}
+
+/// Process all entries of a directory and return the subdirectories still to walk
+fn visit_dir(item: &WorkItem, state: &RootState, stats: &mut DirStats, hard_links: &HardLinkTracker) -> Vec<WorkItem> {
+    let mut subdirs = vec![];
+    if state.exhausted.load(Ordering::Relaxed) {
+        return subdirs;
+    }
+
+    // 检查目录深度限制
+    if item.depth > state.budget.max_depth {
+        if !state.depth_exceeded.swap(true, Ordering::Relaxed) {
+            eprintln!("{} Warning: Maximum directory depth ({}) exceeded for {}. Size calculation might be incomplete.",
+                     "SKIP".yellow(), state.budget.max_depth, item.path.display());
+        }
+        return subdirs;
+    }
+
+    let Ok(entries) = std::fs::read_dir(&item.path) else {
+        return subdirs;
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            subdirs.push(WorkItem {
+                root: item.root,
+                path: entry.path(),
+                depth: item.depth + 1,
+            });
+        } else if file_type.is_file() {
+            // 检查文件数量限制
+            if state.files.fetch_add(1, Ordering::Relaxed) >= state.budget.max_files as u64 {
+                if !state.exhausted.swap(true, Ordering::Relaxed) {
+                    eprintln!("{} Warning: Maximum file count ({}) exceeded for {}. Size calculation might be incomplete.",
+                             "SKIP".yellow(), state.budget.max_files, item.path.display());
+                }
+                return vec![];
+            }
+            if let Ok(metadata) = entry.metadata() {
+                stats.add_file(&metadata, hard_links);
+            }
+        }
+    }
+    subdirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_tree(root: &std::path::Path, dirs: usize, files_per_dir: usize) {
+        for d in 0..dirs {
+            let dir = root.join(format!("d{}/nested", d));
+            std::fs::create_dir_all(&dir).unwrap();
+            for f in 0..files_per_dir {
+                std::fs::write(dir.join(format!("f{}", f)), vec![0u8; 10]).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_concurrent_measurements_share_threads() {
+        let dir = tempfile::TempDir::new().unwrap();
+        make_tree(dir.path(), 20, 5);
+        let engine = SizeEngine::new(2);
+
+        let counts: Vec<u64> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..6)
+                .map(|_| {
+                    let engine = engine.clone();
+                    let root = dir.path().to_path_buf();
+                    scope.spawn(move || engine.measure(root, SizeBudget::UNLIMITED, &HardLinkTracker::default()).file_count)
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(counts, vec![100; 6]);
+        // 所有名额在统计结束后归还
+        assert_eq!(*engine.permits.available.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_idle_workers_wait_for_new_work() {
+        // 每个任务只产生一个新任务，其余线程大部分时间都在等待
+        let queues = WorkQueues::new(4, [0u32]);
+        let processed = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for worker in 0..4 {
+                let queues = &queues;
+                let processed = &processed;
+                scope.spawn(move || {
+                    while let Some(n) = queues.next(worker) {
+                        processed.fetch_add(1, Ordering::Relaxed);
+                        if n < 1000 {
+                            queues.push(worker, vec![n + 1]);
+                        }
+                        queues.done();
+                    }
+                });
+            }
+        });
+        assert_eq!(processed.into_inner(), 1001);
+    }
+
+    #[test]
+    fn test_measure_all_keeps_roots_separate() {
+        let a = tempfile::TempDir::new().unwrap();
+        let b = tempfile::TempDir::new().unwrap();
+        make_tree(a.path(), 20, 5);
+        make_tree(b.path(), 3, 2);
+        let single_file = b.path().join("d0/nested/f0");
+
+        let engine = SizeEngine::new(4);
+        let links = HardLinkTracker::default();
+        let results = engine.measure_all(
+            vec![
+                (a.path().to_path_buf(), SizeBudget::UNLIMITED),
+                (b.path().to_path_buf(), SizeBudget::UNLIMITED),
+                (a.path().join("missing"), SizeBudget::UNLIMITED),
+            ],
+            &links,
+        );
+
+        assert_eq!(results[0].file_count, 100);
+        assert_eq!(results[0].size, 1000);
+        assert_eq!(results[1].file_count, 6);
+        assert_eq!(results[2], DirStats::default());
+        assert!(!results[0].truncated);
+
+        let file_stats = engine.measure(single_file, SizeBudget::UNLIMITED, &HardLinkTracker::default());
+        assert_eq!(file_stats.size, 10);
+    }
+
+    #[test]
+    fn test_measure_respects_budget() {
+        let dir = tempfile::TempDir::new().unwrap();
+        make_tree(dir.path(), 10, 10);
+        let engine = SizeEngine::new(2);
+
+        let budget = SizeBudget { max_depth: usize::MAX, max_files: 15 };
+        let stats = engine.measure(dir.path().to_path_buf(), budget, &HardLinkTracker::default());
+        assert!(stats.truncated);
+        assert!(stats.file_count <= 15);
+
+        // 文件位于第 2 层，深度为 1 时无法统计到
+        let budget = SizeBudget { max_depth: 1, max_files: usize::MAX };
+        let stats = engine.measure(dir.path().to_path_buf(), budget, &HardLinkTracker::default());
+        assert!(stats.truncated);
+        assert_eq!(stats.file_count, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_does_not_follow_symlinked_root() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = dir.path().join("store");
+        make_tree(&store, 2, 5);
+        let link = dir.path().join("node_modules");
+        std::os::unix::fs::symlink(&store, &link).unwrap();
+
+        let stats = SizeEngine::new(2).measure(link, SizeBudget::UNLIMITED, &HardLinkTracker::default());
+        assert_eq!(stats.file_count, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dedupes_hard_links() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a"), vec![1u8; 8192]).unwrap();
+        std::fs::hard_link(dir.path().join("a"), dir.path().join("b")).unwrap();
+
+        let engine = SizeEngine::new(2);
+        let links = HardLinkTracker::default();
+        let stats = engine.measure(dir.path().to_path_buf(), SizeBudget::UNLIMITED, &links);
+        assert_eq!(stats.size, 8192);
+        assert_eq!(stats.file_count, 1);
+        assert!(stats.disk_size >= 8192);
+
+        // 同一次运行中再次统计时，已出现过的硬链接文件不再计入
+        let again = engine.measure(dir.path().to_path_buf(), SizeBudget::UNLIMITED, &links);
+        assert_eq!(again.size, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sparse_file_disk_size() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = std::fs::File::create(dir.path().join("sparse")).unwrap();
+        file.set_len(64 * 1024 * 1024).unwrap();
+
+        let stats = SizeEngine::new(1).measure(dir.path().to_path_buf(), SizeBudget::UNLIMITED, &HardLinkTracker::default());
+        assert_eq!(stats.size, 64 * 1024 * 1024);
+        assert!(stats.disk_size < stats.size);
+    }
+}
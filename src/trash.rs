@@ -0,0 +1,205 @@
+use crate::utils::{format_timestamp, unix_now};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The freedesktop.org home trash directory (`$XDG_DATA_HOME/Trash`, by default `~/.local/share/Trash`)
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn home_trash_dir() -> io::Result<PathBuf> {
+    dirs::data_dir()
+        .map(|dir| dir.join("Trash"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Cannot determine the user data directory"))
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn home_trash_dir() -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "--trash is only supported on freedesktop.org systems",
+    ))
+}
+
+/// Percent-encode a path as the trash specification requires
+fn encode_trash_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_encoded_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Deletion date required by the trash specification: local time as `YYYY-MM-DDThh:mm:ss`, without a time zone
+#[cfg(unix)]
+fn deletion_date(secs: u64) -> String {
+    let time = secs as libc::time_t;
+    // SAFETY: `tm` 是普通的 C 结构体，`localtime_r` 只写入传入的指针
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return format_timestamp(secs);
+    }
+    format_timestamp(secs.saturating_add_signed(tm.tm_gmtoff as i64))
+}
+
+#[cfg(not(unix))]
+fn deletion_date(secs: u64) -> String {
+    format_timestamp(secs)
+}
+
+/// Trash of the filesystem holding `path`: `$topdir/.Trash-$uid`, where `$topdir` is that filesystem's mount point
+#[cfg(unix)]
+fn volume_trash_dir(path: &Path) -> io::Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+    let device = std::fs::symlink_metadata(path)?.dev();
+    let mut topdir = path;
+    for ancestor in path.ancestors().skip(1) {
+        if std::fs::metadata(ancestor)?.dev() != device {
+            break;
+        }
+        topdir = ancestor;
+    }
+    // SAFETY: getuid 总是成功
+    let uid = unsafe { libc::getuid() };
+    Ok(topdir.join(format!(".Trash-{}", uid)))
+}
+
+/// Move `path` to the trash and return its new location under `files/`
+///
+/// The name is claimed by writing the `.trashinfo` with `create_new` before renaming. When `path` and `trash_dir` are on different
+/// filesystems, `.Trash-$uid` under that filesystem's mount point is used instead; if the move still fails an error is returned, never a copy or delete.
+pub fn move_to_trash(path: &Path, trash_dir: &Path) -> io::Result<PathBuf> {
+    let absolute = std::path::absolute(path)?;
+    let result = match trash_into(&absolute, trash_dir) {
+        #[cfg(unix)]
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            volume_trash_dir(&absolute).and_then(|volume_trash| trash_into(&absolute, &volume_trash))
+        }
+        result => result,
+    };
+    result.map_err(|e| {
+        io::Error::new(e.kind(), format!("Cannot move {} to trash: {}", path.display(), e))
+    })
+}
+
+/// Move the absolute path `path` into the given trash directory
+fn trash_into(path: &Path, trash_dir: &Path) -> io::Result<PathBuf> {
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    std::fs::create_dir_all(&files_dir)?;
+    std::fs::create_dir_all(&info_dir)?;
+
+    let base_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?
+        .to_os_string();
+
+    for attempt in 0u32.. {
+        let mut name = base_name.clone();
+        if attempt > 0 {
+            name.push(format!(".{}", attempt));
+        }
+        let mut info_name = name.clone();
+        info_name.push(".trashinfo");
+        let info_path = info_dir.join(&info_name);
+
+        let mut info_file = match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        };
+        write!(
+            info_file,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_trash_path(path),
+            deletion_date(unix_now())
+        )?;
+
+        let trashed = files_dir.join(&name);
+        if trashed.exists() {
+            std::fs::remove_file(&info_path)?;
+            continue;
+        }
+        if let Err(e) = std::fs::rename(path, &trashed) {
+            std::fs::remove_file(&info_path)?;
+            return Err(e);
+        }
+        return Ok(trashed);
+    }
+    unreachable!()
+}
+
+/// Move `trashed` back to `original` and delete its `.trashinfo`; returns `Ok(false)` when the original location already exists
+pub fn restore_from_trash(trashed: &Path, original: &Path) -> io::Result<bool> {
+    if std::fs::symlink_metadata(original).is_ok() {
+        return Ok(false);
+    }
+    std::fs::rename(trashed, original)?;
+
+    // files/<name> 对应 info/<name>.trashinfo
+    if let (Some(files_dir), Some(name)) = (trashed.parent(), trashed.file_name()) {
+        if let Some(trash_dir) = files_dir.parent() {
+            let mut info_name = name.to_os_string();
+            info_name.push(".trashinfo");
+            let _ = std::fs::remove_file(trash_dir.join("info").join(info_name));
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_trash_path() {
+        assert_eq!(encode_trash_path(Path::new("/home/me/a b/venv")), "/home/me/a%20b/venv");
+    }
+
+    #[test]
+    fn test_move_to_trash_writes_trashinfo() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let trash = dir.path().join("Trash");
+        for _ in 0..2 {
+            let venv = dir.path().join("venv");
+            std::fs::create_dir_all(venv.join("bin")).unwrap();
+            move_to_trash(&venv, &trash).unwrap();
+            assert!(!venv.exists());
+        }
+
+        assert!(trash.join("files/venv/bin").exists());
+        assert!(trash.join("files/venv.1/bin").exists());
+        let info = std::fs::read_to_string(trash.join("info/venv.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+        assert!(info.contains("/venv\nDeletionDate="));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_volume_trash_dir_is_on_the_same_filesystem() {
+        use std::os::unix::fs::MetadataExt;
+        let dir = tempfile::TempDir::new().unwrap();
+        let trash = volume_trash_dir(dir.path()).unwrap();
+        let topdir = trash.parent().unwrap();
+        assert!(dir.path().starts_with(topdir));
+        assert_eq!(std::fs::metadata(topdir).unwrap().dev(), std::fs::metadata(dir.path()).unwrap().dev());
+        assert!(trash.file_name().unwrap().to_str().unwrap().starts_with(".Trash-"));
+    }
+
+    #[test]
+    fn test_restore_from_trash() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let trash = dir.path().join("Trash");
+        let venv = dir.path().join("venv");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+
+        let trashed = move_to_trash(&venv, &trash).unwrap();
+        assert!(restore_from_trash(&trashed, &venv).unwrap());
+        assert!(venv.join("bin").exists());
+        assert!(!trash.join("info/venv.trashinfo").exists());
+        assert!(!restore_from_trash(&trashed, &venv).unwrap());
+    }
+}
@@ -1,89 +1,71 @@
 use which::which;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use crate::config::ConfigError; // 引入 ConfigError
+use crate::safety::SafetyPolicy;
+use serde::{Deserialize, Deserializer};
 
 pub fn command_exists(cmd: &str) -> bool {
     which(cmd).is_ok()
 }
 
-/// Validate and sanitize a path to prevent directory traversal attacks
-pub fn validate_and_sanitize_path(path_str: &str) -> Result<PathBuf, ConfigError> {
-    let path = Path::new(path_str);
-    
-    // Check for empty path
-    if path_str.is_empty() {
-        return Err(ConfigError::InvalidConfig(
-            "Path cannot be empty".to_string()
-        ));
+/// Deserialize either a single value or a list, so both `path = "a"` and `path = ["a", "b"]` are accepted
+pub fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
     }
 
-    // Normalize path separators for cross-platform checking
-    let path_str_normalized = path_str.replace('\\', "/");
-    
-    // Check for path traversal patterns (cross-platform)
-    if path_str_normalized.contains("../") || path_str_normalized.contains("~/") {
-        return Err(ConfigError::InvalidConfig(
-            "Path traversal (../) or home directory (~) not allowed".to_string()
-        ));
-    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
 
-    // Check for absolute paths that might be dangerous
-    if path.is_absolute() {
-        // For security, we'll restrict absolute paths to common safe directories
-        let path_str_lower = path_str_normalized.to_lowercase();
-        let dangerous_patterns = [
-            // Unix system directories
-            "/etc", "/usr", "/bin", "/sbin", "/lib", "/boot", "/dev", "/proc",
-            "/sys", "/root", "/var", "/opt", "/tmp", "/home", "/mnt", "/media",
-            // Windows system directories
-            "c:/windows", "c:\\windows", "d:/windows", "d:\\windows",
-            "c:/program files", "c:\\program files", "c:/program files (x86)", "c:\\program files (x86)",
-            "c:/system32", "c:\\system32", "c:/winnt", "c:\\winnt",
-            // macOS system directories
-            "/applications", "/library", "/system", "/users",
-            // Common dangerous paths
-            "/windows", "/program files", "/system32", "/winnt",
-        ];
-        
-        // Check for exact matches and prefix matches
-        for pattern in &dangerous_patterns {
-            let pattern_lower = pattern.to_lowercase();
-            if path_str_lower.starts_with(&pattern_lower) {
-                // Check if it's exactly the pattern or pattern followed by a separator
-                let path_len = path_str_lower.len();
-                let pattern_len = pattern_lower.len();
-                if path_len == pattern_len ||
-                   path_str_lower.chars().nth(pattern_len).map_or(false, |c| c == '/' || c == '\\') {
-                    return Err(ConfigError::InvalidConfig(
-                        format!("Access to system directory '{}' not allowed", pattern)
-                    ));
-                }
-            }
-        }
-    }
+/// Current Unix timestamp in seconds
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
 
-    // Always attempt canonicalization for security - this is critical
-    let canonical_path = match path.canonicalize() {
-        Ok(path) => path,
-        Err(_) => {
-            return Err(ConfigError::InvalidConfig(
-                "Path does not exist or cannot be accessed".to_string()
-            ));
-        }
-    };
-
-    // Ensure path doesn't escape current working directory
-    if let Ok(current_dir) = std::env::current_dir() {
-        if let Ok(current_canonical) = current_dir.canonicalize() {
-            if !canonical_path.starts_with(&current_canonical) {
-                return Err(ConfigError::InvalidConfig(
-                    "Path must be within the current directory tree".to_string()
-                ));
-            }
-        }
-    }
+/// Format a Unix timestamp as `YYYY-MM-DDThh:mm:ss` in UTC
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // 公历日期换算（Howard Hinnant 的 civil_from_days 算法）
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
 
-    Ok(canonical_path)
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Validate and canonicalize a root path against the default safety policy
+pub fn validate_and_sanitize_path(path_str: &str) -> Result<PathBuf, ConfigError> {
+    SafetyPolicy::default().check(Path::new(path_str))
 }
 
 /// Validate exclude directory names to prevent injection attacks
@@ -132,11 +114,38 @@ pub fn validate_exclude_dir_name(dir_name: &str) -> Result<(), ConfigError> {
     Ok(())
 }
 
+/// Validate an `--exclude` / `--include` glob pattern relative to the root
+pub fn validate_path_pattern(pattern: &str) -> Result<(), ConfigError> {
+    if pattern.is_empty() {
+        return Err(ConfigError::InvalidConfig(
+            "Path pattern cannot be empty".to_string()
+        ));
+    }
+
+    let normalized = pattern.replace('\\', "/");
+
+    // Patterns are always relative to the root: no absolute paths, home directory or `..`
+    if normalized.starts_with('/') || normalized.starts_with('~') || Path::new(pattern).is_absolute() {
+        return Err(ConfigError::InvalidConfig(
+            format!("Path pattern must be relative to the root: '{}'", pattern)
+        ));
+    }
+    if normalized.split('/').any(|component| component == "..") {
+        return Err(ConfigError::InvalidConfig(
+            format!("Path traversal (..) not allowed in pattern: '{}'", pattern)
+        ));
+    }
+
+    glob::Pattern::new(&normalized).map_err(|e| ConfigError::InvalidConfig(
+        format!("Invalid path pattern '{}': {}", pattern, e)
+    ))?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
 
     #[test]
     fn test_command_exists() {
@@ -147,23 +156,40 @@ mod tests {
         assert!(!command_exists("a-command-that-does-not-exist"));
     }
 
-    // Security tests for path validation
     #[test]
-    fn test_validate_and_sanitize_path_reject_traversal() {
-        // Test various path traversal attempts
-        assert!(validate_and_sanitize_path("../etc/passwd").is_err());
-        assert!(validate_and_sanitize_path("../../etc").is_err());
-        assert!(validate_and_sanitize_path("../../../usr/bin").is_err());
-        assert!(validate_and_sanitize_path("file/../etc/passwd").is_err());
-        assert!(validate_and_sanitize_path("dir/../../etc").is_err());
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00");
+        assert_eq!(format_timestamp(951782400), "2000-02-29T00:00:00");
+        assert_eq!(format_timestamp(1792331464), "2026-10-18T13:51:04");
     }
 
+    // Security tests for path validation
     #[test]
-    fn test_validate_and_sanitize_path_reject_home_directory() {
-        // Test home directory access attempts
-        assert!(validate_and_sanitize_path("~/etc/passwd").is_err());
-        assert!(validate_and_sanitize_path("~/").is_err());
-        assert!(validate_and_sanitize_path("~/Documents").is_err());
+    fn test_validate_and_sanitize_path_reject_traversal() {
+        // `..` is resolved before the rules apply, so traversal can't escape them
+        let dir = tempfile::TempDir::new().unwrap();
+        let projects = dir.path().join("projects");
+        std::fs::create_dir_all(projects.join("app")).unwrap();
+        std::fs::create_dir_all(projects.join("release")).unwrap();
+        std::fs::create_dir_all(dir.path().join("other")).unwrap();
+        let policy = SafetyPolicy {
+            protected_roots: vec![projects.join("release")],
+            allowed_roots: vec![projects.clone()],
+            allow_root: false,
+        };
+
+        let err = policy.check(&projects.join("app/../release")).unwrap_err().to_string();
+        assert!(err.contains("(protected_roots)"), "{}", err);
+        let err = policy.check(&projects.join("app/../../other")).unwrap_err().to_string();
+        assert!(err.contains("outside every allowed root"), "{}", err);
+        assert_eq!(
+            policy.check(&projects.join("release/../app")).unwrap(),
+            projects.join("app").canonicalize().unwrap()
+        );
+
+        let up = "../".repeat(dir.path().components().count());
+        let err = policy.check(&dir.path().join(up).join("etc")).unwrap_err().to_string();
+        assert!(err.contains("within '/etc' (built-in protected root)"), "{}", err);
     }
 
     #[test]
@@ -263,6 +289,21 @@ mod tests {
         assert!(validate_exclude_dir_name("lpt1").is_err());
     }
 
+    #[test]
+    fn test_validate_path_pattern() {
+        assert!(validate_path_pattern("clients/legacy/*").is_ok());
+        assert!(validate_path_pattern("**/third_party/**").is_ok());
+        assert!(validate_path_pattern("apps/web").is_ok());
+
+        assert!(validate_path_pattern("").is_err());
+        assert!(validate_path_pattern("/etc/*").is_err());
+        assert!(validate_path_pattern("~/projects").is_err());
+        assert!(validate_path_pattern("../outside/*").is_err());
+        assert!(validate_path_pattern("a/../../b").is_err());
+        assert!(validate_path_pattern("a\\..\\b").is_err());
+        assert!(validate_path_pattern("[unclosed").is_err());
+    }
+
     #[test]
     fn test_validate_exclude_dir_name_too_long() {
         // Test name length limit